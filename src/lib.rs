@@ -1,10 +1,21 @@
-use futures::future::join_all;
-use cynic::{QueryBuilder, MutationBuilder, serde_json::Value};
-use reqwest::{ClientBuilder, header};
+//! Bulk operations on a Wiki.js instance through its GraphQL API.
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::time::{Duration, Instant};
+use futures::{stream, FutureExt, StreamExt, TryStreamExt};
+use futures::future::FusedFuture;
+use cynic::{QueryBuilder, MutationBuilder, GraphQlError, Operation};
+use reqwest::{ClientBuilder, StatusCode, header};
 use itertools::{Itertools};
-use anyhow::{Result, bail};
 
-use queries::{ResponseStatus, PageListItem, ListAllPages, ListAllPagesArguments, MoveSinglePage, MoveSinglePageArguments, GetWikiTitle};
+pub use queries::{ListAllPagesArguments, ListFields, PageOrderBy, PageOrderByDirection, ResponseStatus, PageListItem, Page, PageTag, PageHistoryResult, PageHistory};
+use queries::{ListAllPages, ListAllPagesMinimal, MoveSinglePage, MoveSinglePageArguments, GetWikiTitle};
+use queries::{ListPageLinks, ListPageLinksArguments};
+use queries::{ListTags, GetServerVersion};
+use queries::{ListPageHistory, ListPageHistoryArguments, RestorePageVersion, RestorePageVersionArguments};
+use queries::{GetSinglePage, GetSinglePageArguments, CreateSinglePage, CreateSinglePageArguments, UpdateSinglePage, UpdateSinglePageArguments};
+use queries::{DeleteSinglePage, DeleteSinglePageArguments};
 
 /// Code for Queries generated using <https://generator.cynic-rs.dev/>. 
 /// The code generation is currently running an unreleased version with some newer syntax.
@@ -36,16 +47,85 @@ use queries::{ResponseStatus, PageListItem, ListAllPages, ListAllPagesArguments,
 )]
 mod queries {
     use super::schema;
+    use chrono::{DateTime, Utc};
+    use cynic::serde::{Serialize, Deserialize};
+
+    // Wiki.js serialises its `Date` scalar as an RFC 3339 timestamp
+    cynic::impl_scalar!(DateTime<Utc>, schema::Date);
 
     // List Pages
 
-    /// (Optional) Tags to filter the list by
+    /// (Optional) Tags, locale & user IDs to filter the list by, and how to
+    /// order & limit it, all done by the wiki
+    /// 
+    /// These are all the arguments Wiki.js 2.x's `pages.list` takes. It has no
+    /// offset and no path, so there's no paging through the list, and prefixes
+    /// are filtered here after every page has been returned
     /// 
     /// Codegen Changes
     /// QueryVariables -> FragmentArguments
-    #[derive(cynic::FragmentArguments, Debug)]
+    #[derive(cynic::FragmentArguments, Clone, Debug, Default, Serialize)]
     pub struct ListAllPagesArguments {
         pub tags: Option<Vec<String>>,
+        pub locale: Option<String>,
+        /// User who last edited the page
+        pub author_id: Option<i32>,
+        /// User who created the page
+        pub creator_id: Option<i32>,
+        /// The wiki orders by ID if not told otherwise
+        pub order_by: Option<PageOrderBy>,
+        pub order_by_direction: Option<PageOrderByDirection>,
+        /// Applied by the wiki after ordering, so the first `limit` pages
+        pub limit: Option<i32>,
+        /// Which of each page's fields to ask for. Not an argument of `pages.list`,
+        /// but of what's selected from it
+        pub fields: ListFields,
+    }
+
+    /// The fields of a `PageListItem` asked for beyond its id & path, all of them by
+    /// default. Those left out are empty, and dated at the epoch, as in the minimal listing
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+    pub struct ListFields {
+        pub locale: bool,
+        pub tags: bool,
+        pub title: bool,
+        pub content_type: bool,
+        pub created_at: bool,
+        pub updated_at: bool,
+    }
+
+    impl ListFields {
+        pub const ALL: ListFields = ListFields {
+            locale: true,
+            tags: true,
+            title: true,
+            content_type: true,
+            created_at: true,
+            updated_at: true,
+        };
+    }
+
+    impl Default for ListFields {
+        fn default() -> Self {
+            ListFields::ALL
+        }
+    }
+
+    #[derive(cynic::Enum, Clone, Copy, Debug, PartialEq, Eq)]
+    #[cynic(graphql_type = "PageOrderBy")]
+    pub enum PageOrderBy {
+        Created,
+        Id,
+        Path,
+        Title,
+        Updated,
+    }
+
+    #[derive(cynic::Enum, Clone, Copy, Debug, PartialEq, Eq)]
+    #[cynic(graphql_type = "PageOrderByDirection")]
+    pub enum PageOrderByDirection {
+        Asc,
+        Desc,
     }
 
     /// ListAllPages Operation type. Wrapper around PageQuery.
@@ -62,7 +142,15 @@ mod queries {
     #[derive(cynic::QueryFragment, Debug)]
     #[cynic(argument_struct = "ListAllPagesArguments")]
     pub struct PageQuery {
-        #[arguments(tags = &args.tags)]
+        #[arguments(
+            tags = &args.tags,
+            locale = &args.locale,
+            author_id = &args.author_id,
+            creator_id = &args.creator_id,
+            order_by = &args.order_by,
+            order_by_direction = &args.order_by_direction,
+            limit = &args.limit
+        )]
         pub list: Vec<PageListItem>,
     }
 
@@ -72,17 +160,123 @@ mod queries {
     /// returns an empty Vec rather than a None, and there's no concept of
     /// a None tag that could be returned either, but the Schema doesn't 
     /// express this adequately to Codegen 
-    #[derive(cynic::QueryFragment, Debug)]
+    /// 
+    /// Its `QueryFragment` is written out rather than derived, to select only the
+    /// `ListFields` in the list's arguments
+    #[derive(Debug, Clone, Serialize, Deserialize)]
     pub struct PageListItem {
         pub id: i32,
         pub path: String,
+        pub locale: String,
         pub tags: Option<Vec<Option<String>>>,
         pub title: Option<String>,
+        /// `markdown` or `html`: the list doesn't say which editor was used,
+        /// only what it produces. Visual, raw HTML & AsciiDoc pages are all `html`
+        pub content_type: String,
+        pub created_at: DateTime<Utc>,
+        pub updated_at: DateTime<Utc>,
     }
 
-    // Page Move
+    impl cynic::QueryFragment for PageListItem {
+        type SelectionSet = cynic::SelectionSet<'static, Self, schema::PageListItem>;
+        type Arguments = ListAllPagesArguments;
+
+        fn fragment(context: cynic::FragmentContext<Self::Arguments>) -> Self::SelectionSet {
+            use cynic::selection_set::{map8, scalar, succeed, succeed_using};
+            use schema::PageListItem as Item;
+            let fields = context.args.fields;
+            let new = |id, path, locale, tags, title, content_type, created_at, updated_at| PageListItem {
+                id,
+                path,
+                locale,
+                tags,
+                title,
+                content_type,
+                created_at,
+                updated_at,
+            };
+            map8(
+                new,
+                Item::id().select(scalar()),
+                Item::path().select(scalar()),
+                match fields.locale {
+                    true => Item::locale().select(scalar()),
+                    false => succeed_using(String::new),
+                },
+                match fields.tags {
+                    true => Item::tags().select(scalar()),
+                    false => succeed(None),
+                },
+                match fields.title {
+                    true => Item::title().select(scalar()),
+                    false => succeed(None),
+                },
+                match fields.content_type {
+                    true => Item::content_type().select(scalar()),
+                    false => succeed_using(String::new),
+                },
+                match fields.created_at {
+                    true => Item::created_at().select(scalar()),
+                    false => succeed_using(DateTime::default),
+                },
+                match fields.updated_at {
+                    true => Item::updated_at().select(scalar()),
+                    false => succeed_using(DateTime::default),
+                },
+            )
+        }
+
+        fn graphql_type() -> String {
+            "PageListItem".to_owned()
+        }
+    }
 
-    /// Full Destination Path & numeric ID of page
+    // Page List, for `ApiVersion::Minimal`
+
+    /// ListAllPages asking only for the fields list & move can't do without,
+    /// and filtering by tags alone.
+    #[derive(cynic::QueryFragment, Debug)]
+    #[cynic(graphql_type = "Query", argument_struct = "ListAllPagesArguments")]
+    pub struct ListAllPagesMinimal {
+        pub pages: Option<PageQueryMinimal>,
+    }
+
+    #[derive(cynic::QueryFragment, Debug)]
+    #[cynic(graphql_type = "PageQuery", argument_struct = "ListAllPagesArguments")]
+    pub struct PageQueryMinimal {
+        #[arguments(tags = &args.tags)]
+        pub list: Vec<PageListItemMinimal>,
+    }
+
+    #[derive(cynic::QueryFragment, Debug)]
+    #[cynic(graphql_type = "PageListItem")]
+    pub struct PageListItemMinimal {
+        pub id: i32,
+        pub path: String,
+        /// Needed to tell whether a move changes a page's locale, & to move it back
+        pub locale: String,
+        pub tags: Option<Vec<Option<String>>>,
+        pub title: Option<String>,
+    }
+
+    /// Fields the minimal listing doesn't ask for are left empty, and dated at the epoch
+    impl From<PageListItemMinimal> for PageListItem {
+        fn from(p: PageListItemMinimal) -> Self {
+            PageListItem {
+                id: p.id,
+                path: p.path,
+                locale: p.locale,
+                tags: p.tags,
+                title: p.title,
+                content_type: String::new(),
+                created_at: DateTime::default(),
+                updated_at: DateTime::default(),
+            }
+        }
+    }
+
+    // Page Move
+    /// Full Destination Path & Locale, and numeric ID of page
     /// 
     /// Codegen Changes
     /// QueryVariables -> FragmentArguments
@@ -90,6 +284,7 @@ mod queries {
     /// Option<i32> -> i32
     #[derive(cynic::FragmentArguments, Debug)]
     pub struct MoveSinglePageArguments {
+        pub destination_locale: String,
         pub destination_path: String,
         pub id: i32,
     }
@@ -104,14 +299,14 @@ mod queries {
     /// Return (sub)type of Successful Page Mutation 
     /// 
     /// Codegen Changes
-    /// `#[arguments(destinationLocale: "en")]` -> `#[arguments(destination_locale = "en")]`
+    /// `#[arguments(destinationLocale: $destinationLocale)]` -> `#[arguments(destination_locale = &args.destination_locale)]`
     /// `#[arguments(destinationPath: $destinationPath)]` -> `#[arguments(destination_path = &args.destination_path)]`
     /// `#[arguments(id: $id)]` -> `#[arguments(id = &args.id)]`
     #[derive(cynic::QueryFragment, Debug)]
     #[cynic(argument_struct = "MoveSinglePageArguments")]
     pub struct PageMutation {
         #[arguments(
-            destination_locale = "en", 
+            destination_locale = &args.destination_locale, 
             destination_path = &args.destination_path, 
             id = &args.id
         )]
@@ -126,7 +321,7 @@ mod queries {
     }
 
     /// Return (sub)type for MoveSinglePage.
-    #[derive(cynic::QueryFragment, Debug)]
+    #[derive(cynic::QueryFragment, Debug, Clone)]
     pub struct ResponseStatus {
         pub error_code: i32,
         pub message: Option<String>,
@@ -134,6 +329,283 @@ mod queries {
         pub succeeded: bool,
     }
 
+    // Single Page
+
+    /// Numeric ID of page
+    #[derive(cynic::FragmentArguments, Debug)]
+    pub struct GetSinglePageArguments {
+        pub id: i32,
+    }
+
+    /// GetSinglePage Operation type. Wrapper around SinglePageQuery.
+    #[derive(cynic::QueryFragment, Debug)]
+    #[cynic(graphql_type = "Query", argument_struct = "GetSinglePageArguments")]
+    pub struct GetSinglePage {
+        pub pages: Option<SinglePageQuery>,
+    }
+
+    /// Return (sub)type of Successful Single Page Query.
+    /// Named so as not to clash with the `PageQuery` used by ListAllPages.
+    #[derive(cynic::QueryFragment, Debug)]
+    #[cynic(graphql_type = "PageQuery", argument_struct = "GetSinglePageArguments")]
+    pub struct SinglePageQuery {
+        #[arguments(id = &args.id)]
+        pub single: Option<Page>,
+    }
+
+    /// Return type for a Single page, including its content
+    #[derive(cynic::QueryFragment, Debug)]
+    pub struct Page {
+        pub path: String,
+        pub title: String,
+        pub description: String,
+        pub content: String,
+        pub editor: String,
+        pub locale: String,
+        pub is_private: bool,
+        pub is_published: bool,
+        pub tags: Vec<Option<PageTag>>,
+        pub updated_at: DateTime<Utc>,
+    }
+
+    /// Tag attached to a Single page, or listed by ListTags
+    #[derive(cynic::QueryFragment, Debug)]
+    pub struct PageTag {
+        pub tag: String,
+        pub title: Option<String>,
+    }
+
+    // Tags
+
+    /// ListTags Operation type. Wrapper around TagsQuery.
+    #[derive(cynic::QueryFragment, Debug)]
+    #[cynic(graphql_type = "Query")]
+    pub struct ListTags {
+        pub pages: Option<TagsQuery>,
+    }
+
+    /// Return (sub)type of Successful Tags Query.
+    /// Named so as not to clash with the `PageQuery` used by ListAllPages.
+    #[derive(cynic::QueryFragment, Debug)]
+    #[cynic(graphql_type = "PageQuery")]
+    pub struct TagsQuery {
+        pub tags: Vec<Option<PageTag>>,
+    }
+
+    // Page Create
+
+    /// Everything needed to create a page. Wiki.js requires all of these.
+    ///
+    /// Schema Changes
+    /// `tags: [String]!` -> `tags: [String!]!` as cynic can't express the former
+    #[derive(cynic::FragmentArguments, Debug)]
+    pub struct CreateSinglePageArguments {
+        pub content: String,
+        pub description: String,
+        pub editor: String,
+        pub is_published: bool,
+        pub is_private: bool,
+        pub locale: String,
+        pub path: String,
+        pub tags: Vec<String>,
+        pub title: String,
+    }
+
+    /// CreateSinglePage Operation type. Wrapper around CreatePageMutation.
+    #[derive(cynic::QueryFragment, Debug)]
+    #[cynic(graphql_type = "Mutation", argument_struct = "CreateSinglePageArguments")]
+    pub struct CreateSinglePage {
+        pub pages: Option<CreatePageMutation>,
+    }
+
+    /// Return (sub)type of Successful Page Create.
+    /// Named so as not to clash with the `PageMutation` used by MoveSinglePage.
+    #[derive(cynic::QueryFragment, Debug)]
+    #[cynic(graphql_type = "PageMutation", argument_struct = "CreateSinglePageArguments")]
+    pub struct CreatePageMutation {
+        #[arguments(
+            content = &args.content,
+            description = &args.description,
+            editor = &args.editor,
+            is_published = &args.is_published,
+            is_private = &args.is_private,
+            locale = &args.locale,
+            path = &args.path,
+            tags = &args.tags,
+            title = &args.title
+        )]
+        pub create: Option<CreatePageResponse>,
+    }
+
+    /// Return type for CreateSinglePage. Like `PageResponse`, with the ID of the new page
+    #[derive(cynic::QueryFragment, Debug)]
+    #[cynic(graphql_type = "PageResponse")]
+    pub struct CreatePageResponse {
+        pub response_result: ResponseStatus,
+        pub page: Option<CreatedPage>,
+    }
+
+    #[derive(cynic::QueryFragment, Debug)]
+    #[cynic(graphql_type = "Page")]
+    pub struct CreatedPage {
+        pub id: i32,
+    }
+
+    // Page Update
+
+    /// Numeric ID of page & the content to replace
+    #[derive(cynic::FragmentArguments, Debug)]
+    pub struct UpdateSinglePageArguments {
+        pub id: i32,
+        pub content: String,
+        pub description: Option<String>,
+        pub tags: Option<Vec<Option<String>>>,
+        pub title: Option<String>,
+    }
+
+    /// UpdateSinglePage Operation type. Wrapper around UpdatePageMutation.
+    #[derive(cynic::QueryFragment, Debug)]
+    #[cynic(graphql_type = "Mutation", argument_struct = "UpdateSinglePageArguments")]
+    pub struct UpdateSinglePage {
+        pub pages: Option<UpdatePageMutation>,
+    }
+
+    /// Return (sub)type of Successful Page Update.
+    #[derive(cynic::QueryFragment, Debug)]
+    #[cynic(graphql_type = "PageMutation", argument_struct = "UpdateSinglePageArguments")]
+    pub struct UpdatePageMutation {
+        #[arguments(
+            id = &args.id,
+            content = &args.content,
+            description = &args.description,
+            tags = &args.tags,
+            title = &args.title
+        )]
+        pub update: Option<PageResponse>,
+    }
+
+    /// Return type for UpdateSinglePage. Wrapper around ResponseStatus
+    #[derive(cynic::QueryFragment, Debug)]
+    pub struct PageResponse {
+        pub response_result: ResponseStatus,
+    }
+
+    // Page Links
+
+    /// Locale to retrieve the links graph for
+    #[derive(cynic::FragmentArguments, Debug)]
+    pub struct ListPageLinksArguments {
+        pub locale: String,
+    }
+
+    /// ListPageLinks Operation type. Wrapper around PageLinksQuery.
+    #[derive(cynic::QueryFragment, Debug)]
+    #[cynic(graphql_type = "Query", argument_struct = "ListPageLinksArguments")]
+    pub struct ListPageLinks {
+        pub pages: Option<PageLinksQuery>,
+    }
+
+    /// Return (sub)type of Successful Page Links Query.
+    /// Named so as not to clash with the `PageQuery` used by ListAllPages.
+    #[derive(cynic::QueryFragment, Debug)]
+    #[cynic(graphql_type = "PageQuery", argument_struct = "ListPageLinksArguments")]
+    pub struct PageLinksQuery {
+        #[arguments(locale = &args.locale)]
+        pub links: Option<Vec<Option<PageLinkItem>>>,
+    }
+
+    /// A page and the pages it links out to.
+    /// Both `path` and `links` are prefixed with the locale, e.g. `en/docs/intro`
+    #[derive(cynic::QueryFragment, Debug)]
+    pub struct PageLinkItem {
+        pub links: Vec<Option<String>>,
+    }
+
+    // Page History
+
+    /// Numeric ID of page
+    #[derive(cynic::FragmentArguments, Debug)]
+    pub struct ListPageHistoryArguments {
+        pub id: i32,
+    }
+
+    /// ListPageHistory Operation type. Wrapper around PageHistoryQuery.
+    #[derive(cynic::QueryFragment, Debug)]
+    #[cynic(graphql_type = "Query", argument_struct = "ListPageHistoryArguments")]
+    pub struct ListPageHistory {
+        pub pages: Option<PageHistoryQuery>,
+    }
+
+    /// Return (sub)type of Successful Page History Query.
+    /// Named so as not to clash with the `PageQuery` used by ListAllPages.
+    #[derive(cynic::QueryFragment, Debug)]
+    #[cynic(graphql_type = "PageQuery", argument_struct = "ListPageHistoryArguments")]
+    pub struct PageHistoryQuery {
+        #[arguments(id = &args.id)]
+        pub history: Option<PageHistoryResult>,
+    }
+
+    /// Versions of a page, newest first
+    #[derive(cynic::QueryFragment, Debug)]
+    pub struct PageHistoryResult {
+        pub trail: Option<Vec<Option<PageHistory>>>,
+        pub total: i32,
+    }
+
+    /// A single version of a page
+    #[derive(cynic::QueryFragment, Debug)]
+    pub struct PageHistory {
+        pub version_id: i32,
+        pub version_date: DateTime<Utc>,
+        pub author_name: String,
+        pub action_type: String,
+    }
+
+    /// Numeric IDs of page & the version to restore
+    #[derive(cynic::FragmentArguments, Debug)]
+    pub struct RestorePageVersionArguments {
+        pub page_id: i32,
+        pub version_id: i32,
+    }
+
+    /// RestorePageVersion Operation type. Wrapper around RestorePageMutation.
+    #[derive(cynic::QueryFragment, Debug)]
+    #[cynic(graphql_type = "Mutation", argument_struct = "RestorePageVersionArguments")]
+    pub struct RestorePageVersion {
+        pub pages: Option<RestorePageMutation>,
+    }
+
+    /// Return (sub)type of Successful Page Restore.
+    #[derive(cynic::QueryFragment, Debug)]
+    #[cynic(graphql_type = "PageMutation", argument_struct = "RestorePageVersionArguments")]
+    pub struct RestorePageMutation {
+        #[arguments(page_id = &args.page_id, version_id = &args.version_id)]
+        pub restore: Option<DefaultResponse>,
+    }
+
+    // Page Delete
+
+    /// Numeric ID of page
+    #[derive(cynic::FragmentArguments, Debug)]
+    pub struct DeleteSinglePageArguments {
+        pub id: i32,
+    }
+
+    /// DeleteSinglePage Operation type. Wrapper around DeletePageMutation.
+    #[derive(cynic::QueryFragment, Debug)]
+    #[cynic(graphql_type = "Mutation", argument_struct = "DeleteSinglePageArguments")]
+    pub struct DeleteSinglePage {
+        pub pages: Option<DeletePageMutation>,
+    }
+
+    /// Return (sub)type of Successful Page Delete.
+    #[derive(cynic::QueryFragment, Debug)]
+    #[cynic(graphql_type = "PageMutation", argument_struct = "DeleteSinglePageArguments")]
+    pub struct DeletePageMutation {
+        #[arguments(id = &args.id)]
+        pub delete: Option<DefaultResponse>,
+    }
+
     // Retrieve Wiki Title
     #[derive(cynic::QueryFragment, Debug)]
     #[cynic(graphql_type = "Query")]
@@ -151,8 +623,26 @@ mod queries {
         pub title: Option<String>,
     }
 
+    // Retrieve Wiki.js version
+    #[derive(cynic::QueryFragment, Debug)]
+    #[cynic(graphql_type = "Query")]
+    pub struct GetServerVersion {
+        pub system: Option<SystemQuery>,
+    }
+
+    #[derive(cynic::QueryFragment, Debug)]
+    pub struct SystemQuery {
+        pub info: Option<SystemInfo>,
+    }
+
+    #[derive(cynic::QueryFragment, Debug)]
+    pub struct SystemInfo {
+        pub current_version: Option<String>,
+    }
+
 }
 
+#[allow(clippy::too_many_arguments)]
 mod schema {
     cynic::use_schema!(r#"src/schema.graphql"#);
 }
@@ -163,128 +653,1034 @@ pub struct ListPages {
     pub pages_returned: usize
 }
 
+#[derive(Debug)]
 pub struct MoveSuccess {
     pub success_count: usize,
-    pub failures: Option<Vec<ResponseStatus>>
+    pub failures: Option<Vec<ResponseStatus>>,
+    /// Ids of the pages which were moved
+    pub moved: Vec<i32>,
+    /// Pages never sent because the move was stopped early
+    pub not_attempted: usize,
+    /// How long each move request took to be answered, in no particular order
+    pub latencies: Vec<Duration>,
+    /// What happened to each page which was sent, in no particular order
+    pub outcomes: Vec<MoveOutcome>
 }
 
-const USER_AGENT: &str = concat!(
-    env!("CARGO_PKG_NAME"),
-    "/",
-    env!("CARGO_PKG_VERSION")
-);
-pub struct Wiki {
-    client: reqwest::Client,
-    endpoint: String
+/// What happened to a single page during a move
+#[derive(Debug)]
+pub struct MoveOutcome {
+    pub id: i32,
+    pub from: String,
+    pub to: String,
+    /// The wiki's answer, or why the request itself failed
+    pub result: Result<ResponseStatus, String>,
+    /// How far a copy-then-delete move got, e.g. `copied, checked, original deleted`
+    pub steps: Option<String>
 }
 
-pub struct WikiConfig {
-    pub api_key: String,
-    pub endpoint: String,
-    pub http2: bool,
-    pub https: bool
+/// What a move changed about a page besides its path, comparing its listing
+/// from before the move with the page the wiki has afterwards
+#[derive(Debug)]
+pub struct MetadataChanges<'a> {
+    pub page: &'a PageListItem,
+    /// The page's locale after the move, if it's not the one it had
+    pub locale: Option<String>,
+    pub title: bool,
+    pub tags: bool,
+    pub updated_at: bool,
 }
 
-impl Wiki {
-    pub fn new(
-        conf: WikiConfig
-    ) -> Wiki {
-        let mut headers = header::HeaderMap::new();
-        
-        let bearer = "Bearer ".to_string() + &conf.api_key;
+#[derive(Debug)]
+pub struct DeleteSuccess {
+    pub success_count: usize,
+    pub failures: Option<Vec<ResponseStatus>>,
+    /// Ids of the pages which were deleted
+    pub deleted: Vec<i32>,
+}
 
-        let mut auth_value = header::HeaderValue::from_str(&bearer).unwrap();
-        auth_value.set_sensitive(true);
-        headers.insert(header::AUTHORIZATION, auth_value);
+#[derive(Debug)]
+pub struct CopySuccess {
+    pub success_count: usize,
+    /// Destination paths which already existed and were left alone
+    pub skipped: Vec<String>,
+    pub failures: Option<Vec<ResponseStatus>>
+}
 
-        let client_builder = ClientBuilder::new()
-        .https_only(conf.https)
-        .user_agent(USER_AGENT)
-        .default_headers(headers);
+/// Everything which can go wrong talking to the wiki
+#[derive(Debug, thiserror::Error)]
+pub enum WikiError {
+    /// The request couldn't be sent, or its response couldn't be read. The reqwest
+    /// error is part of the message, so it isn't also given as the source
+    #[error("{}: {0}", match .0.url() {
+        Some(url) => format!("Failed to reach the wiki at {}", url),
+        None => "Request to the wiki failed".to_owned(),
+    })]
+    Network(reqwest::Error),
+    /// The wiki rejected the API key
+    #[error("The wiki rejected the API key")]
+    Auth,
+    /// Still answered 429 Too Many Requests after retrying
+    #[error("The wiki is still refusing requests after {} retries, try a lower --rate", MAX_RETRIES)]
+    RateLimited,
+    /// The wiki answered with another error status, e.g. a proxy's 502 page. Holds the start of the body
+    #[error("The wiki answered {0}{}", match .1.is_empty() {
+        true => String::new(),
+        false => format!(": {}", .1),
+    })]
+    Status(StatusCode, String),
+    /// The settings given to `WikiBuilder` can't be used
+    #[error("{0}")]
+    Config(String),
+    /// The wiki answered with errors, and no data
+    #[error("The wiki returned errors: {}", .0.join(", "))]
+    GraphQl(Vec<String>),
+    /// The response didn't match the query, or its body wasn't JSON at all
+    #[error("Unexpected response from the wiki")]
+    Decode(#[source] Box<dyn std::error::Error + Send + Sync>),
+    /// The response was missing part of what was asked for
+    #[error("{0}")]
+    NoData(String),
+    /// The wiki doesn't support an operation
+    #[error("{0}")]
+    Unsupported(String),
+    /// Some pages were moved before requests for the others failed
+    #[error("Some, but not all, requests failed. The move may be partially complete: {} pages were moved.", .0.success_count)]
+    PartialMove(MoveSuccess),
+    /// Some pages were copied before requests for the others failed
+    #[error("Some, but not all, requests failed. The copy may be partially complete: {} pages were copied.", .0.success_count)]
+    PartialCopy(CopySuccess),
+    /// Some pages were deleted before requests for the others failed
+    #[error("Some, but not all, requests failed. The delete may be partially complete: {} pages were deleted.", .0.success_count)]
+    PartialDelete(DeleteSuccess),
+}
 
-        let client = match conf.http2 {
-            true => {client_builder.http2_prior_knowledge()}
-            false => {client_builder}
-        }.build().expect("Failed to initialise http client");
-        
-        Wiki {
-            client,
-            endpoint: conf.endpoint
+impl From<reqwest::Error> for WikiError {
+    fn from(e: reqwest::Error) -> Self {
+        WikiError::Network(e)
+    }
+}
+
+impl From<cynic::DecodeError> for WikiError {
+    fn from(e: cynic::DecodeError) -> Self {
+        WikiError::Decode(Box::new(e))
+    }
+}
+
+impl From<Vec<GraphQlError>> for WikiError {
+    /// Wiki.js reports a bad or underprivileged API key as a `Forbidden` error
+    fn from(errors: Vec<GraphQlError>) -> Self {
+        match errors.iter().any(|e| e.message == "Forbidden") {
+            true => WikiError::Auth,
+            false => WikiError::GraphQl(errors.into_iter().map(|e| e.message).collect()),
         }
     }
+}
 
-    pub async fn get_wiki_title(&self) -> Result<String> {
-        let op = GetWikiTitle::build(());
-        let raw_response = self.client
-            .post(&self.endpoint)
-            .json(&op)
-            .send()
-            .await
-            .expect("Response had a problem");
+/// Path a page will end up at when `prefix` is replaced by `destination`.
+/// With an empty `prefix`, or one the path doesn't begin with, the whole path
+/// is nested under `destination`.
+/// 
+/// `as_folder` treats `destination` as a folder, joining it to the rest of the
+/// path with exactly one slash. Otherwise the two are simply concatenated, which
+/// can rename part of a path segment. Paths needn't be ASCII:
+/// 
+/// ```
+/// # use chrono::Utc;
+/// # let page = wiki::PageListItem {
+/// #     id: 1, path: "café/menü".to_owned(), locale: "de".to_owned(), tags: None, title: None,
+/// #     content_type: "markdown".to_owned(), created_at: Utc::now(), updated_at: Utc::now(),
+/// # };
+/// assert_eq!(wiki::compute_destination(&page, "café", "archiv/café", true), "archiv/café/menü");
+/// assert_eq!(wiki::compute_destination(&page, "café/", "archiv", true), "archiv/menü");
+/// assert_eq!(wiki::compute_destination(&page, "café/", "archiv", false), "archivmenü");
+/// assert_eq!(wiki::compute_destination(&page, "caf", "bistro", false), "bistroé/menü");
+/// ```
+pub fn compute_destination(page: &PageListItem, prefix: &str, destination: &str, as_folder: bool) -> String {
+    replace_prefix(&page.path, prefix, destination, as_folder)
+}
+
+/// Whether a page looks private: a segment of its path is one of the `markers`, or it
+/// has one as a tag, ignoring case either way. Only whole segments & tags count:
+/// 
+/// ```
+/// # use chrono::Utc;
+/// # let markers = ["private".to_owned()];
+/// # let page = |path: &str, tags: &[&str]| wiki::PageListItem {
+/// #     id: 1, path: path.to_owned(), locale: "en".to_owned(), title: None,
+/// #     tags: Some(tags.iter().map(|t| Some(t.to_string())).collect()),
+/// #     content_type: "markdown".to_owned(), created_at: Utc::now(), updated_at: Utc::now(),
+/// # };
+/// assert!(wiki::is_private(&page("docs/Private/x", &[]), &markers));
+/// assert!(wiki::is_private(&page("private", &[]), &markers));
+/// assert!(wiki::is_private(&page("docs/notes", &["Private"]), &markers));
+/// assert!(!wiki::is_private(&page("ops/privatebeta/notes", &[]), &markers));
+/// assert!(!wiki::is_private(&page("companyprivateers", &["privateers"]), &markers));
+/// 
+/// let markers = ["restricted".to_owned(), "confidential".to_owned()];
+/// assert!(wiki::is_private(&page("hr/Confidential/pay", &[]), &markers));
+/// assert!(!wiki::is_private(&page("docs/private/x", &[]), &markers));
+/// ```
+pub fn is_private(page: &PageListItem, markers: &[String]) -> bool {
+    let is_marker = |word: &str| markers.iter().any(|m| word.eq_ignore_ascii_case(m));
+    let is_private_path = page.path.split('/').any(is_marker);
+    let is_private_tag = page.tags.iter().flatten().flatten().any(|tag| is_marker(tag));
+    is_private_path || is_private_tag
+}
 
-        let json = raw_response.json().await.expect("Json decoding issue");
+fn replace_prefix(path: &str, prefix: &str, destination: &str, as_folder: bool) -> String {
+    let rest = path.strip_prefix(prefix).unwrap_or(path);
+    if !as_folder {
+        return destination.to_owned() + rest;
+    }
+    match (destination.trim_end_matches('/'), rest.trim_start_matches('/')) {
+        ("", rest) => rest.to_owned(),
+        (destination, "") => destination.to_owned(),
+        (destination, rest) => format!("{}/{}", destination, rest),
+    }
+}
 
-        let response = op.decode_response(json).unwrap();
+/// A sed-style `s/old/new/` substitution applied to a page's whole path, e.g.
+/// `s/^docs\/([^\/]+)/docs\/v2\/$1/`. `old` is a regex, and `new` can refer to its
+/// capture groups as `$1`, `${name}` and so on; a trailing `g` replaces every match
+/// rather than the first. Any character after the `s` is the delimiter, and a
+/// backslash escapes it.
+///
+/// ```
+/// let transform: wiki::PathTransform = r"s/^docs\/(api|sdk)/docs\/v2\/$1/".parse().unwrap();
+/// assert_eq!(transform.apply("docs/api/intro"), "docs/v2/api/intro");
+/// assert_eq!(transform.apply("docs/guide"), "docs/guide");
+/// assert!("s/docs(/x/".parse::<wiki::PathTransform>().is_err());
+/// ```
+#[derive(Clone, Debug)]
+pub struct PathTransform {
+    find: regex::Regex,
+    replace: String,
+    global: bool,
+}
 
-        match response.data {
-            Some(gwt) => match gwt.site {
-                Some(sq) => match sq.config {
-                    Some(sc) => match sc.title {
-                        Some(t) => Ok(t),
-                        None => bail!("No title"),
-                    },
-                    None => bail!("No config returned"),
-                },
-                None => bail!("No site returned")
-            }
-            None => bail!("No data in response")
-         }
+impl PathTransform {
+    pub fn apply(&self, path: &str) -> String {
+        match self.global {
+            true => self.find.replace_all(path, self.replace.as_str()).into_owned(),
+            false => self.find.replacen(path, 1, self.replace.as_str()).into_owned(),
+        }
     }
+}
 
-    pub async fn list_pages(&self, prefix: &str, tags: Option<Vec<String>> ) -> Result<ListPages> {
-        let op = ListAllPages::build(
-            ListAllPagesArguments{tags}
-        );
-        
-        let raw_response = self.client
-            .post(&self.endpoint)
-            .json(&op)
-            .send()
-            .await
-            .expect("Response had a problem");
+impl std::str::FromStr for PathTransform {
+    type Err = String;
 
-        let json = raw_response.json().await.expect("Json decoding issue");
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut chars = s.chars().peekable();
+        let delimiter = match (chars.next(), chars.next()) {
+            (Some('s'), Some(d)) if d != '\\' => d,
+            _ => return Err(format!("`{}` should look like s/old/new/", s)),
+        };
 
-        let response = op.decode_response(json).unwrap();
-        
-        // unwrap like it's christmas morning
-        let page_list = match response.data {
-            Some(lap) => match lap.pages {
-                Some(pq) => pq.list,
-                None => bail!("No pages returned: GraphQlResponse{{data: Some(ListAllPages{{pages: None}}}}")
+        let mut parts = Vec::new();
+        let mut part = String::new();
+        while let Some(c) = chars.next() {
+            match c {
+                '\\' => part.push(chars.next_if_eq(&delimiter).unwrap_or(c)),
+                c if c == delimiter => parts.push(std::mem::take(&mut part)),
+                c => part.push(c),
             }
-            None => bail!("No data in response: GraphQlResponse{{data: None}}")
-         };
+        }
+        parts.push(part);
 
-         let pages_returned = page_list.len();
+        match parts.as_slice() {
+            [find, _, _] if find.is_empty() => Err(format!("`{}` has nothing to replace", s)),
+            [find, replace, flags] if flags.is_empty() || flags == "g" => Ok(PathTransform {
+                find: regex::Regex::new(find).map_err(|e| format!("`{}` isn't a valid regex: {}", find, e))?,
+                replace: replace.clone(),
+                global: flags == "g",
+            }),
+            [_, _, flags] => Err(format!("`{}` is not a flag, only g is", flags)),
+            _ => Err(format!("`{}` should look like s/old/new/, with three delimiters", s)),
+        }
+    }
+}
 
-         let filtered_pages = page_list
-            .into_iter()
-            .filter(|p| {p.path.starts_with(prefix)})
-            .sorted_by(|a, b| Ord::cmp(&a.path, &b.path))
-            .collect::<Vec<queries::PageListItem>>();
+impl std::fmt::Display for PathTransform {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let escape = |s: &str| s.replace('/', "\\/");
+        write!(f, "s/{}/{}/{}", escape(self.find.as_str()), escape(&self.replace), if self.global { "g" } else { "" })
+    }
+}
 
-        Ok( ListPages{ pages: filtered_pages, pages_returned})
+/// Where `move_pages` sends each page
+#[derive(Clone, Debug)]
+pub enum Destination {
+    /// Replace `prefix` at the start of each path, see `compute_destination`
+    Prefix { prefix: String, destination: String, as_folder: bool },
+    /// Rewrite each whole path
+    Transform(PathTransform),
+    /// Each page's new path, by its current one, e.g. from a reviewed plan.
+    /// Pages not in it stay where they are
+    Exact(HashMap<String, String>),
+}
+
+impl Destination {
+    /// Path `page` will end up at
+    pub fn of(&self, page: &PageListItem) -> String {
+        self.of_path(&page.path)
     }
 
-    pub async fn move_pages(
-        &self, 
-        pages: &Vec<queries::PageListItem>, 
-        prefix: &str, 
-        destination: &str,
-    ) -> Result<MoveSuccess> {
+    /// Path a page at `path` would end up at
+    pub fn of_path(&self, path: &str) -> String {
+        match self {
+            Destination::Prefix { prefix, destination, as_folder } => replace_prefix(path, prefix, destination, *as_folder),
+            Destination::Transform(transform) => transform.apply(path),
+            Destination::Exact(paths) => paths.get(path).cloned().unwrap_or_else(|| path.to_owned()),
+        }
+    }
+}
+
+/// Marks a page as private from within its content, found by a deep scan
+pub const PRIVATE_MARKER: &str = "<!-- private -->";
+
+/// Locale pages are moved into, unless another is given
+pub const DEFAULT_LOCALE: &str = "en";
+
+/// Path segments & tags which mark a page as private, unless others are given
+pub const DEFAULT_SENSITIVE_MARKERS: [&str; 1] = ["private"];
+
+/// `error_code` of the status recorded for a page which hit the per-page timeout
+pub const TIMEOUT_ERROR_CODE: i32 = -1;
+
+/// `error_code` of the status recorded for a page whose copy didn't check out during a copy-then-delete move
+pub const UNVERIFIED_ERROR_CODE: i32 = -2;
+
+/// `error_code` of the status recorded for a page whose move request itself failed, e.g. the connection dropped
+pub const REQUEST_ERROR_CODE: i32 = -3;
+
+/// Major version of Wiki.js whose schema this build was generated from
+pub const SUPPORTED_MAJOR_VERSION: &str = "2";
+
+/// Whether a version reported by `get_server_version`, e.g. `2.5.300`, should work with this build
+pub fn is_supported_version(version: &str) -> bool {
+    version.trim_start_matches('v').split('.').next() == Some(SUPPORTED_MAJOR_VERSION)
+}
+
+/// How many move mutations are in flight at once.
+/// 
+/// Wiki.js 2 has no bulk move mutation (there's nothing like `moveMany` in
+/// schema.graphql), so each page is moved by its own request.
+const MOVE_CONCURRENCY: usize = 16;
+
+/// Moves in flight when `Concurrency::Auto` starts
+const AUTO_START_CONCURRENCY: usize = 2;
+
+/// `Concurrency::Auto` measures throughput over waves of this many moves per request in flight
+const AUTO_WAVE_ROUNDS: usize = 4;
+
+/// How much faster a wave must be than the best so far for `Concurrency::Auto` to keep ramping up
+const AUTO_IMPROVEMENT: f64 = 1.1;
+
+/// How many move mutations are in flight at once
+#[derive(Clone, Copy, Debug)]
+pub enum Concurrency {
+    Fixed(usize),
+    /// Start with a few, doubling while throughput improves and halving when
+    /// requests error or time out, never going above `max`
+    Auto { max: usize },
+}
+
+impl Default for Concurrency {
+    fn default() -> Self {
+        Concurrency::Fixed(MOVE_CONCURRENCY)
+    }
+}
+
+/// Which queries to send, for servers whose schema differs from the one this
+/// crate was built against
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum ApiVersion {
+    /// The Wiki.js 2.x schema bundled with this crate
+    #[default]
+    Current,
+    /// List pages asking only for their ID, path, locale, title & tags, so a renamed
+    /// or missing field elsewhere doesn't break decoding. Pages have no editor
+    /// or dates, and can only be filtered by tags on the wiki
+    Minimal,
+}
+
+impl std::str::FromStr for ApiVersion {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "current" | "2" => Ok(ApiVersion::Current),
+            "minimal" => Ok(ApiVersion::Minimal),
+            _ => Err(format!("`{}` isn't an API version, try current or minimal", s)),
+        }
+    }
+}
+
+/// How many pages' content a deep scan fetches at once
+const DEEP_SCAN_CONCURRENCY: usize = 8;
+
+/// How many moved pages `metadata_changes` fetches again
+pub const METADATA_SAMPLE: usize = 5;
+
+/// Times a request is retried while the wiki answers 429 Too Many Requests
+const MAX_RETRIES: u32 = 5;
+
+/// How much of an error status's body is kept for `WikiError::Status`
+const STATUS_BODY_CHARS: usize = 200;
+
+/// Longest wait before the first retry when there's no `Retry-After`, doubling each time
+const RETRY_BASE_DELAY: Duration = Duration::from_millis(500);
+
+const USER_AGENT: &str = concat!(
+    env!("CARGO_PKG_NAME"),
+    "/",
+    env!("CARGO_PKG_VERSION")
+);
+/// Await `f`, giving up after `limit` if there is one
+async fn within<F: Future>(limit: Option<Duration>, f: F) -> Option<F::Output> {
+    match limit {
+        Some(limit) => tokio::time::timeout(limit, f).await.ok(),
+        None => Some(f.await),
+    }
+}
+
+/// How long a 429 response asks us to wait, given in seconds or as an HTTP-date
+fn retry_after(response: &reqwest::Response) -> Option<Duration> {
+    let value = response.headers().get(header::RETRY_AFTER)?.to_str().ok()?.trim();
+    match value.parse::<u64>() {
+        Ok(seconds) => Some(Duration::from_secs(seconds)),
+        Err(_) => {
+            let at = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+            Some((at.with_timezone(&chrono::Utc) - chrono::Utc::now()).to_std().unwrap_or_default())
+        }
+    }
+}
+
+/// Exponential backoff with full jitter, so concurrent moves don't all retry at once
+fn backoff(attempt: u32) -> Duration {
+    (RETRY_BASE_DELAY * 2u32.pow(attempt)).mul_f64(fastrand::f64())
+}
+
+/// Spaces requests out so no more than `rate` are sent per second
+struct RateLimiter {
+    interval: Duration,
+    next: tokio::sync::Mutex<tokio::time::Instant>,
+}
+
+impl RateLimiter {
+    fn new(rate: f64) -> RateLimiter {
+        RateLimiter {
+            interval: Duration::from_secs_f64(1.0 / rate),
+            next: tokio::sync::Mutex::new(tokio::time::Instant::now()),
+        }
+    }
+
+    /// Wait for the next free slot
+    async fn wait(&self) {
+        let slot = {
+            let mut next = self.next.lock().await;
+            let slot = (*next).max(tokio::time::Instant::now());
+            *next = slot + self.interval;
+            slot
+        };
+        tokio::time::sleep_until(slot).await;
+    }
+}
+
+/// A page sent to be moved, the wiki's answer (`None` if it timed out), and how long it took
+type MoveResponse<'p> = (&'p PageListItem, Result<Option<ResponseStatus>, WikiError>, Duration);
+
+/// Gather the answers to `sent` moves into a `MoveSuccess`, each outcome going from a
+/// page's listed path to its path under `to`. Moves never answered were not attempted
+fn collect_moves(
+    sent: usize,
+    responses: Vec<MoveResponse<'_>>,
+    to: &Destination,
+) -> Result<MoveSuccess, WikiError> {
+    let not_attempted = sent - responses.len();
+    let latencies = responses.iter().map(|(_, _, l)| *l).collect();
+
+    let mut moved = Vec::new();
+    let mut failures = Vec::new();
+    let mut errors = Vec::new();
+    let mut outcomes = Vec::new();
+    for (page, response, _) in responses {
+
+        let result = match response {
+            Ok(Some(rs)) if rs.succeeded => {
+                moved.push(page.id);
+                Ok(rs)
+            }
+            Ok(Some(rs)) => {
+                failures.push(rs.clone());
+                Ok(rs)
+            }
+            Ok(None) => {
+                failures.push(timed_out(page));
+                Ok(timed_out(page))
+            }
+            Err(e) => {
+                let message = e.to_string();
+                failures.push(request_failed(page, &e));
+                errors.push(e);
+                Err(message)
+            }
+        };
+        outcomes.push(MoveOutcome{
+            id: page.id,
+            from: page.path.clone(),
+            to: to.of(page),
+            result,
+            steps: None,
+        });
+    }
+
+    let move_success = MoveSuccess{
+        success_count: moved.len(), 
+        failures: match failures.len() {0 => None, _ => Some(failures)},
+        moved,
+        not_attempted,
+        latencies,
+        outcomes
+    };
+
+    // errors are among the failures too, so when nothing moved the caller decides from those
+    match (errors.is_empty(), move_success.success_count) {
+        (true, _) | (_, 0) => Ok(move_success),
+        _ => Err(WikiError::PartialMove(move_success)),
+    }
+}
+
+fn timed_out(page: &PageListItem) -> ResponseStatus {
+    ResponseStatus {
+        error_code: TIMEOUT_ERROR_CODE,
+        message: Some(format!("Moving {} timed out", page.path)),
+        slug: "Timeout".to_owned(),
+        succeeded: false,
+    }
+}
+
+fn request_failed(page: &PageListItem, e: &WikiError) -> ResponseStatus {
+    ResponseStatus {
+        error_code: REQUEST_ERROR_CODE,
+        message: Some(format!("Moving {} failed: {}", page.path, e)),
+        slug: "RequestFailed".to_owned(),
+        succeeded: false,
+    }
+}
+
+fn copy_unverified(path: &str) -> ResponseStatus {
+    ResponseStatus {
+        error_code: UNVERIFIED_ERROR_CODE,
+        message: Some(format!("The copy at {} wasn't found, or its content differs", path)),
+        slug: "CopyUnverified".to_owned(),
+        succeeded: false,
+    }
+}
+
+/// Steps to add once a copy which shouldn't be kept was deleted, or not
+fn copy_undone(undo: Result<ResponseStatus, WikiError>) -> &'static str {
+    match undo {
+        Ok(rs) if rs.succeeded => "copy deleted again",
+        _ => "copy left in place, as deleting it failed",
+    }
+}
+
+pub struct Wiki {
+    client: reqwest::Client,
+    endpoint: String,
+    dump_query: bool,
+    limiter: Option<RateLimiter>,
+    concurrency: Concurrency,
+    api_version: ApiVersion,
+    locale: String,
+    sensitive_markers: Vec<String>,
+    /// Fetched at most once, by `get_wiki_title`
+    title: tokio::sync::OnceCell<String>,
+}
+
+pub struct WikiConfig {
+    pub api_key: String,
+    pub endpoint: String,
+    pub http2: bool,
+    pub https: bool,
+    /// Print operations to stderr before they're sent
+    pub dump_query: bool,
+    /// Requests per second to send when listing & moving pages. Unlimited if `None`
+    pub rate: Option<f64>,
+    /// Idle connections to keep open to the wiki. Unlimited if `None`
+    pub pool_size: Option<usize>,
+    /// Close idle connections after this long. 90 seconds if `None`
+    pub pool_idle_timeout: Option<Duration>,
+    /// Ping HTTP/2 connections this often to keep them open
+    pub http2_keep_alive: Option<Duration>,
+    /// How many moves to send at once
+    pub concurrency: Concurrency,
+    /// Which queries to send
+    pub api_version: ApiVersion,
+    /// Locale pages are moved into, and links are read from. `DEFAULT_LOCALE` in configs without one
+    pub locale: String,
+    /// Path segments & tags which mark a page as private, see `is_private`
+    pub sensitive_markers: Vec<String>,
+}
+
+/// Builds a `Wiki`, returning an error rather than panicking on bad settings.
+/// 
+/// ```no_run
+/// # async fn run() -> Result<(), wiki::WikiError> {
+/// let wiki = wiki::Wiki::builder()
+///     .endpoint("https://wiki.example.com/graphql")
+///     .api_key("...")
+///     .build()?;
+/// println!("{}", wiki.get_wiki_title().await?);
+/// # Ok(()) }
+/// ```
+pub struct WikiBuilder {
+    api_key: Option<String>,
+    endpoint: Option<String>,
+    http2: bool,
+    https: bool,
+    dump_query: bool,
+    rate: Option<f64>,
+    timeout: Option<Duration>,
+    proxy: Option<String>,
+    pool_size: Option<usize>,
+    pool_idle_timeout: Option<Duration>,
+    http2_keep_alive: Option<Duration>,
+    concurrency: Concurrency,
+    api_version: ApiVersion,
+    locale: String,
+    sensitive_markers: Vec<String>,
+}
+
+impl WikiBuilder {
+    pub fn api_key(mut self, api_key: impl Into<String>) -> WikiBuilder {
+        self.api_key = Some(api_key.into());
+        self
+    }
+
+    /// The GraphQL endpoint, e.g. `https://wiki.example.com/graphql`
+    pub fn endpoint(mut self, endpoint: impl Into<String>) -> WikiBuilder {
+        self.endpoint = Some(endpoint.into());
+        self
+    }
+
+    /// Use HTTP/2 without negotiating it first. Off by default
+    pub fn http2(mut self, http2: bool) -> WikiBuilder {
+        self.http2 = http2;
+        self
+    }
+
+    /// Refuse to connect over plain HTTP. On by default
+    pub fn https(mut self, https: bool) -> WikiBuilder {
+        self.https = https;
+        self
+    }
+
+    /// Print operations to stderr before they're sent
+    pub fn dump_query(mut self, dump_query: bool) -> WikiBuilder {
+        self.dump_query = dump_query;
+        self
+    }
+
+    /// Requests per second to send when listing & moving pages
+    pub fn rate(mut self, rate: f64) -> WikiBuilder {
+        self.rate = Some(rate);
+        self
+    }
+
+    /// Give up on any request which isn't answered within `timeout`
+    pub fn timeout(mut self, timeout: Duration) -> WikiBuilder {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Send every request through this proxy, e.g. `http://proxy.example.com:8080`
+    pub fn proxy(mut self, proxy: impl Into<String>) -> WikiBuilder {
+        self.proxy = Some(proxy.into());
+        self
+    }
+
+    /// Keep no more than this many idle connections open to the wiki
+    pub fn pool_size(mut self, pool_size: usize) -> WikiBuilder {
+        self.pool_size = Some(pool_size);
+        self
+    }
+
+    /// Close idle connections after this long
+    pub fn pool_idle_timeout(mut self, timeout: Duration) -> WikiBuilder {
+        self.pool_idle_timeout = Some(timeout);
+        self
+    }
+
+    /// Ping HTTP/2 connections this often, so one connection can carry many moves
+    pub fn http2_keep_alive(mut self, interval: Duration) -> WikiBuilder {
+        self.http2_keep_alive = Some(interval);
+        self
+    }
+
+    /// How many moves to send at once. 16 by default
+    pub fn concurrency(mut self, concurrency: Concurrency) -> WikiBuilder {
+        self.concurrency = concurrency;
+        self
+    }
+
+    /// Which queries to send. `ApiVersion::Current` by default
+    pub fn api_version(mut self, api_version: ApiVersion) -> WikiBuilder {
+        self.api_version = api_version;
+        self
+    }
+
+    /// Locale pages are moved into, unless a move gives another. `DEFAULT_LOCALE` by default
+    pub fn locale(mut self, locale: impl Into<String>) -> WikiBuilder {
+        self.locale = locale.into();
+        self
+    }
+
+    /// Path segments & tags which mark a page as private. `DEFAULT_SENSITIVE_MARKERS` by default
+    pub fn sensitive_markers(mut self, markers: Vec<String>) -> WikiBuilder {
+        self.sensitive_markers = markers;
+        self
+    }
+
+    pub fn build(self) -> Result<Wiki, WikiError> {
+        let api_key = self.api_key.ok_or_else(|| WikiError::Config("No API key given".to_owned()))?;
+        let endpoint = self.endpoint.ok_or_else(|| WikiError::Config("No endpoint given".to_owned()))?;
+
+        let mut headers = header::HeaderMap::new();
+        
+        let bearer = "Bearer ".to_string() + &api_key;
+
+        let mut auth_value = header::HeaderValue::from_str(&bearer)
+            .map_err(|_| WikiError::Config("The API key contains characters which can't be sent in a header".to_owned()))?;
+        auth_value.set_sensitive(true);
+        headers.insert(header::AUTHORIZATION, auth_value);
+
+        let mut client_builder = ClientBuilder::new()
+        .https_only(self.https)
+        .user_agent(USER_AGENT)
+        .default_headers(headers);
+
+        if self.http2 {
+            client_builder = client_builder.http2_prior_knowledge();
+        }
+        if let Some(timeout) = self.timeout {
+            client_builder = client_builder.timeout(timeout);
+        }
+        if let Some(pool_size) = self.pool_size {
+            client_builder = client_builder.pool_max_idle_per_host(pool_size);
+        }
+        if let Some(timeout) = self.pool_idle_timeout {
+            client_builder = client_builder.pool_idle_timeout(timeout);
+        }
+        if let Some(interval) = self.http2_keep_alive {
+            client_builder = client_builder
+                .http2_keep_alive_interval(interval)
+                .http2_keep_alive_while_idle(true);
+        }
+        if let Some(proxy) = self.proxy {
+            let proxy = reqwest::Proxy::all(&proxy)
+                .map_err(|e| WikiError::Config(format!("Invalid proxy `{}`: {}", proxy, e)))?;
+            client_builder = client_builder.proxy(proxy);
+        }
+
+        let client = client_builder.build()
+            .map_err(|e| WikiError::Config(format!("Failed to initialise http client: {}", e)))?;
+        
+        Ok(Wiki {
+            client,
+            endpoint,
+            dump_query: self.dump_query,
+            limiter: self.rate.map(RateLimiter::new),
+            concurrency: self.concurrency,
+            api_version: self.api_version,
+            locale: self.locale,
+            sensitive_markers: self.sensitive_markers,
+            title: tokio::sync::OnceCell::new(),
+        })
+    }
+}
+
+impl Wiki {
+    pub fn builder() -> WikiBuilder {
+        WikiBuilder {
+            api_key: None,
+            endpoint: None,
+            http2: false,
+            https: true,
+            dump_query: false,
+            rate: None,
+            timeout: None,
+            proxy: None,
+            pool_size: None,
+            pool_idle_timeout: None,
+            http2_keep_alive: None,
+            concurrency: Concurrency::default(),
+            api_version: ApiVersion::default(),
+            locale: DEFAULT_LOCALE.to_owned(),
+            sensitive_markers: DEFAULT_SENSITIVE_MARKERS.map(String::from).to_vec(),
+        }
+    }
+
+    /// Panics if the API key can't be sent in a header; use `Wiki::builder` to get an error instead
+    pub fn new(
+        conf: WikiConfig
+    ) -> Wiki {
+        WikiBuilder {
+            api_key: Some(conf.api_key),
+            endpoint: Some(conf.endpoint),
+            http2: conf.http2,
+            https: conf.https,
+            dump_query: conf.dump_query,
+            rate: conf.rate,
+            timeout: None,
+            proxy: None,
+            pool_size: conf.pool_size,
+            pool_idle_timeout: conf.pool_idle_timeout,
+            http2_keep_alive: conf.http2_keep_alive,
+            concurrency: conf.concurrency,
+            api_version: conf.api_version,
+            locale: conf.locale,
+            sensitive_markers: conf.sensitive_markers,
+        }
+        .build()
+        .expect("Failed to initialise http client")
+    }
+
+    /// Wait until the rate limit, if any, allows another request
+    async fn throttle(&self) {
+        if let Some(limiter) = &self.limiter {
+            limiter.wait().await;
+        }
+    }
+
+    /// Print `op` to stderr when `dump_query` is set. The API key is never printed.
+    fn dump(&self, op: &impl cynic::serde::Serialize) {
+        if self.dump_query {
+            eprintln!(
+                "POST {} (Authorization: [redacted])\n{}",
+                self.endpoint,
+                cynic::serde_json::to_string_pretty(op).unwrap_or_default()
+            );
+        }
+    }
+
+    /// Send `op` and decode the data in the response.
+    /// 
+    /// GraphQL errors are only returned when there's no data, as Wiki.js often
+    /// sends both.
+    /// 
+    /// When the wiki answers 429 Too Many Requests, wait as long as its
+    /// `Retry-After` header asks (or back off) and try again.
+    async fn send<'a, T: 'a>(&self, op: &Operation<'a, T>) -> Result<T, WikiError> {
+        let mut attempt = 0;
+        let raw_response = loop {
+            let raw_response = self.client
+                .post(&self.endpoint)
+                .json(op)
+                .send()
+                .await?;
+
+            if raw_response.status() != StatusCode::TOO_MANY_REQUESTS || attempt == MAX_RETRIES {
+                break raw_response;
+            }
+            tokio::time::sleep(retry_after(&raw_response).unwrap_or_else(|| backoff(attempt))).await;
+            attempt += 1;
+        };
+
+        match raw_response.status() {
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => return Err(WikiError::Auth),
+            StatusCode::TOO_MANY_REQUESTS => return Err(WikiError::RateLimited),
+            // an error page isn't worth decoding, but its text may say what went wrong
+            status if !status.is_success() => {
+                let body = raw_response.text().await.unwrap_or_default();
+                return Err(WikiError::Status(status, body.trim().chars().take(STATUS_BODY_CHARS).collect()));
+            }
+            _ => {}
+        }
+
+        // a body which arrived but isn't JSON is the wiki's answer, rather than a network failure
+        let body = raw_response.json().await.map_err(|e| match e.is_decode() {
+            true => WikiError::Decode(Box::new(e)),
+            false => WikiError::Network(e),
+        })?;
+        let response = op.decode_response(body)?;
+
+        match (response.data, response.errors) {
+            (Some(data), _) => Ok(data),
+            (None, Some(errors)) if !errors.is_empty() => Err(errors.into()),
+            (None, _) => Err(WikiError::NoData("No data in response".to_owned()))
+        }
+    }
+
+    /// The GraphQL endpoint requests are sent to
+    pub fn endpoint(&self) -> &str {
+        &self.endpoint
+    }
+
+    /// Locale pages are moved into when a move doesn't give one
+    pub fn locale(&self) -> &str {
+        &self.locale
+    }
+
+    /// The wiki's title. Only the first call asks the wiki, later ones reuse its answer
+    pub async fn get_wiki_title(&self) -> Result<String, WikiError> {
+        self.title.get_or_try_init(|| async {
+            let op = GetWikiTitle::build(());
+            self.dump(&op);
+
+            match self.send(&op).await?.site {
+                Some(sq) => match sq.config {
+                    Some(sc) => match sc.title {
+                        Some(t) => Ok(t),
+                        None => Err(WikiError::NoData("No title".to_owned())),
+                    },
+                    None => Err(WikiError::NoData("No config returned".to_owned())),
+                },
+                None => Err(WikiError::NoData("No site returned".to_owned()))
+            }
+        }).await.cloned()
+    }
+
+    /// Send `requests` of the lightest query there is, for the wiki's title,
+    /// `limit` at a time, and time how long each takes to be answered. For
+    /// gauging the connection before a big move; nothing is changed, or cached
+    pub async fn time_requests(&self, requests: usize, limit: usize) -> Vec<Result<Duration, WikiError>> {
+        let op = &GetWikiTitle::build(());
+        self.dump(op);
+
+        stream::iter(0..requests)
+            .then(|_| self.throttle())
+            .map(|_| async move {
+                let started = Instant::now();
+                self.send(op).await.map(|_| started.elapsed())
+            })
+            .buffer_unordered(limit.max(1))
+            .collect()
+            .await
+    }
+
+    /// The version of Wiki.js the server is running. Needs an API key allowed to see system info
+    pub async fn get_server_version(&self) -> Result<String, WikiError> {
+        let op = GetServerVersion::build(());
+        self.dump(&op);
+
+        match self.send(&op).await?.system.and_then(|sq| sq.info).and_then(|si| si.current_version) {
+            Some(v) => Ok(v),
+            None => Err(WikiError::NoData("No version returned".to_owned())),
+        }
+    }
+
+    /// Every page with all of `tags`, in the order the wiki returns them
+    async fn fetch_pages(&self, args: ListAllPagesArguments) -> Result<Vec<PageListItem>, WikiError> {
+        let pages = match self.api_version {
+            ApiVersion::Current => {
+                let op = ListAllPages::build(args);
+                self.dump(&op);
+                self.throttle().await;
+                self.send(&op).await?.pages.map(|pq| pq.list)
+            }
+            ApiVersion::Minimal => {
+                if args.locale.is_some() || args.author_id.is_some() || args.creator_id.is_some()
+                    || args.order_by.is_some() || args.limit.is_some()
+                {
+                    return Err(WikiError::Unsupported(
+                        "The minimal API version can only filter pages by tags, and can't order or limit them".to_owned()
+                    ));
+                }
+                let op = ListAllPagesMinimal::build(args);
+                self.dump(&op);
+                self.throttle().await;
+                self.send(&op).await?.pages.map(|pq| pq.list.into_iter().map(PageListItem::from).collect())
+            }
+        };
+
+        match pages {
+            Some(list) => Ok(list),
+            None => Err(WikiError::NoData("No pages returned".to_owned()))
+        }
+    }
+
+    pub async fn list_pages(&self, prefix: &str, tags: Option<Vec<String>> ) -> Result<ListPages, WikiError> {
+        self.list_pages_where(prefix, ListAllPagesArguments{ tags, ..Default::default() }).await
+    }
+
+    /// Pages beginning with `prefix`, filtered by the wiki on everything in `args`.
+    /// Sorted by path, unless `args` asks the wiki to order them
+    pub async fn list_pages_where(&self, prefix: &str, args: ListAllPagesArguments) -> Result<ListPages, WikiError> {
+        let ordered = args.order_by.is_some();
+        let page_list = self.fetch_pages(args).await?;
+
+         let pages_returned = page_list.len();
+
+         let mut filtered_pages = page_list
+            .into_iter()
+            .filter(|p| {p.path.starts_with(prefix)})
+            .collect::<Vec<queries::PageListItem>>();
+         if !ordered {
+            filtered_pages.sort_by(|a, b| Ord::cmp(&a.path, &b.path));
+         }
+
+        Ok( ListPages{ pages: filtered_pages, pages_returned})
+    }
 
-        let trim = prefix.len();
+    /// Pages beginning with `prefix`, unsorted, yielded as soon as they arrive.
+    /// 
+    /// Wiki.js returns every page in a single response, as its list query can't be
+    /// paged (see `ListAllPagesArguments`), so for now that's all at once, but
+    /// nothing waits on sorting the whole list.
+    pub fn stream_pages<'a>(
+        &'a self,
+        prefix: &'a str,
+        args: ListAllPagesArguments,
+    ) -> impl futures::Stream<Item = Result<PageListItem, WikiError>> + 'a {
+        stream::once(self.fetch_pages(args))
+            .map_ok(|pages| stream::iter(pages.into_iter().map(Ok)))
+            .try_flatten()
+            .try_filter(move |p| futures::future::ready(p.path.starts_with(prefix)))
+    }
+
+    /// Send a single move, giving up after `timeout`. `None` if it timed out
+    async fn send_move(
+        &self,
+        op: &Operation<'_, MoveSinglePage>,
+        timeout: Option<Duration>,
+    ) -> Result<Option<ResponseStatus>, WikiError> {
+        let data = match within(timeout, self.send(op)).await {
+            Some(data) => data?,
+            None => return Ok(None),
+        };
+        match data.pages.and_then(|ptm| ptm.move_).and_then(|dr| dr.response_result) {
+            Some(rs) => Ok(Some(rs)),
+            None => Err(WikiError::NoData("No move result returned".to_owned()))
+        }
+    }
+
+    /// Move each page to its path under `to`, in `locale`, or the configured locale if `None`.
+    /// 
+    /// Normally every move is attempted at once and failures are collected.
+    /// With `fail_fast`, pages are moved one at a time, in order, stopping at the
+    /// first failure; so the first `success_count` pages are the ones which moved.
+    /// 
+    /// Once `stop` resolves no more moves are sent, but those already in
+    /// flight are waited for. The rest are counted in `not_attempted`.
+    /// A move not answered within `timeout` is recorded as a failure with
+    /// `TIMEOUT_ERROR_CODE`.
+    /// 
+    /// If requests fail after some pages were moved, the error is a
+    /// `WikiError::PartialMove` saying what was done.
+    pub async fn move_pages(
+        &self, 
+        pages: &[queries::PageListItem], 
+        to: &Destination,
+        locale: Option<&str>,
+        fail_fast: bool,
+        timeout: Option<Duration>,
+        stop: impl Future<Output = ()>,
+    ) -> Result<MoveSuccess, WikiError> {
+        let locale = locale.unwrap_or(&self.locale);
 
         // generate an op for each page
         let ops = pages
@@ -293,89 +1689,838 @@ impl Wiki {
                 MoveSinglePage::build(
                     MoveSinglePageArguments{
                         id: p.id, 
-                        destination_path: destination.to_owned() + &p.path[trim..]
+                        destination_locale: locale.to_owned(),
+                        destination_path: to.of(p)
                     }
                 )
             })
             .collect::<Vec<_>>();
 
-        let requests = ops.iter().map(|op| {
-            self.client
-                .post(&self.endpoint)
-                .json(op)
-                .send()
-        });
+        if let Some(op) = ops.first() {
+            self.dump(op);
+        }
+
+        let stop = stop.fuse();
+        futures::pin_mut!(stop);
+
+        if fail_fast {
+            let mut moved = Vec::new();
+            let mut latencies = Vec::new();
+            let mut outcomes = Vec::new();
+            for (op, page) in ops.iter().zip(pages) {
+                if stop.as_mut().now_or_never().is_some() {
+                    break;
+                }
+                self.throttle().await;
+                let success_count = moved.len();
+                let started = Instant::now();
+                let status = self.send_move(op, timeout).await;
+                latencies.push(started.elapsed());
+                let outcome = |result| MoveOutcome{
+                    id: page.id,
+                    from: page.path.clone(),
+                    to: to.of(page),
+                    result,
+                    steps: None,
+                };
+
+                // a request which failed is a failure like any other, as in `collect_moves`
+                let failure = match status {
+                    Ok(Some(rs)) if rs.succeeded => {
+                        moved.push(page.id);
+                        outcomes.push(outcome(Ok(rs)));
+                        continue;
+                    }
+                    Ok(Some(rs)) => {
+                        outcomes.push(outcome(Ok(rs.clone())));
+                        rs
+                    }
+                    Ok(None) => {
+                        outcomes.push(outcome(Ok(timed_out(page))));
+                        timed_out(page)
+                    }
+                    Err(e) => {
+                        outcomes.push(outcome(Err(e.to_string())));
+                        request_failed(page, &e)
+                    }
+                };
+                return Ok(MoveSuccess{
+                    success_count,
+                    failures: Some(vec![failure]),
+                    not_attempted: pages.len() - success_count - 1,
+                    moved,
+                    latencies,
+                    outcomes,
+                });
+            }
+            return Ok(MoveSuccess{
+                success_count: moved.len(),
+                failures: None,
+                not_attempted: pages.len() - moved.len(),
+                moved,
+                latencies,
+                outcomes,
+            });
+        }
+
+        let responses = match self.concurrency {
+            Concurrency::Fixed(limit) => self.send_moves(ops.iter().zip(pages), limit, timeout, stop).await,
+            Concurrency::Auto { max } => {
+                let mut limit = AUTO_START_CONCURRENCY.min(max);
+                let mut best = 0.0;
+                let mut responses = Vec::new();
+                let mut items = ops.iter().zip(pages).peekable();
+                while items.peek().is_some() && !stop.is_terminated() {
+                    let started = Instant::now();
+                    let wave = items.by_ref().take(limit * AUTO_WAVE_ROUNDS);
+                    let wave = self.send_moves(wave, limit, timeout, stop.as_mut()).await;
+                    let throughput = wave.len() as f64 / started.elapsed().as_secs_f64();
+
+                    // a page the wiki refused isn't a sign of load, an error or timeout is
+                    let struggling = wave.iter().any(|(_, r, _)| !matches!(r, Ok(Some(_))));
+                    limit = match (struggling, throughput > best * AUTO_IMPROVEMENT) {
+                        (true, _) => (limit / 2).max(1),
+                        (false, true) => (limit * 2).min(max),
+                        (false, false) => limit, // plateaued
+                    };
+                    if !struggling {
+                        best = f64::max(best, throughput);
+                    }
+                    responses.extend(wave);
+                }
+                responses
+            }
+        };
+
+        collect_moves(ops.len(), responses, to)
+    }
+
+    /// Move the pages `moves` says were moved back to where they were, each into the
+    /// locale it was in. `pages` are the pages as listed before the move.
+    /// 
+    /// For undoing a move which only partly succeeded. The result's outcomes go
+    /// from each page's new path back to its old one, and a rollback which itself
+    /// partly fails is a `WikiError::PartialMove` like any other.
+    pub async fn rollback_moves(&self, pages: &[queries::PageListItem], moves: &MoveSuccess) -> Result<MoveSuccess, WikiError> {
+        // where each moved page is now
+        let now_at = moves.outcomes
+            .iter()
+            .filter(|o| moves.moved.contains(&o.id))
+            .map(|o| (o.id, o.to.clone()))
+            .collect::<HashMap<_, _>>();
+        let originals = pages.iter().filter(|p| now_at.contains_key(&p.id)).collect::<Vec<_>>();
+        let back = Destination::Exact(originals.iter().map(|p| (now_at[&p.id].clone(), p.path.clone())).collect());
+        let moved = originals
+            .iter()
+            .map(|p| queries::PageListItem { path: now_at[&p.id].clone(), ..(*p).clone() })
+            .collect::<Vec<_>>();
+
+        let ops = moved
+            .iter()
+            .map(|p| {
+                MoveSinglePage::build(
+                    MoveSinglePageArguments{
+                        id: p.id,
+                        destination_locale: p.locale.clone(),
+                        destination_path: back.of(p),
+                    }
+                )
+            })
+            .collect::<Vec<_>>();
+
+        if let Some(op) = ops.first() {
+            self.dump(op);
+        }
+
+        let responses = self.send_moves(ops.iter().zip(&moved), self.steady_concurrency(), None, std::future::pending()).await;
+        collect_moves(ops.len(), responses, &back)
+    }
+
+
+    /// Move each page by copying it to its destination in `locale` (or the configured one), checking the copy has the
+    /// same content, then deleting the original. For wikis whose move mutation
+    /// can't be relied on. The moved pages have new IDs.
+    /// 
+    /// If the copy doesn't check out, or the original can't be deleted, the copy
+    /// is deleted again so the page is only where it was. Each outcome's `steps`
+    /// say how far the page got. As with `move_pages`, no more pages are started
+    /// once `stop` resolves, and a partial failure is a `WikiError::PartialMove`.
+    pub async fn copy_then_delete_pages(
+        &self,
+        pages: &[queries::PageListItem],
+        to: &Destination,
+        locale: Option<&str>,
+        stop: impl Future<Output = ()>,
+    ) -> Result<MoveSuccess, WikiError> {
+        let locale = locale.unwrap_or(&self.locale);
+        let responses = stream::iter(pages)
+            .then(|page| self.throttle().map(move |_| page))
+            .take_until(stop)
+            .map(|page| {
+                let started = Instant::now();
+                self.copy_then_delete(page, to, locale)
+                    .map(move |r| (page, r, started.elapsed()))
+            })
+            .buffer_unordered(self.steady_concurrency())
+            .collect::<Vec<_>>()
+            .await;
+
+        let not_attempted = pages.len() - responses.len();
+        let latencies = responses.iter().map(|(_, _, l)| *l).collect();
+
+        let mut moved = Vec::new();
+        let mut failures = Vec::new();
+        let mut errors = Vec::new();
+        let mut outcomes = Vec::new();
+        for (page, response, _) in responses {
+            let (result, steps) = match response {
+                Ok((rs, steps)) => {
+                    match rs.succeeded {
+                        true => moved.push(page.id),
+                        false => failures.push(rs.clone()),
+                    }
+                    (Ok(rs), Some(steps))
+                }
+                Err(e) => {
+                    let message = e.to_string();
+                    failures.push(request_failed(page, &e));
+                    errors.push(e);
+                    (Err(message), None)
+                }
+            };
+            outcomes.push(MoveOutcome{
+                id: page.id,
+                from: page.path.clone(),
+                to: to.of(page),
+                result,
+                steps,
+            });
+        }
+
+        let move_success = MoveSuccess{
+            success_count: moved.len(),
+            failures: match failures.len() {0 => None, _ => Some(failures)},
+            moved,
+            not_attempted,
+            latencies,
+            outcomes
+        };
+
+        // errors are among the failures too, so when nothing moved the caller decides from those
+        match (errors.is_empty(), move_success.success_count) {
+            (true, _) | (_, 0) => Ok(move_success),
+            _ => Err(WikiError::PartialMove(move_success)),
+        }
+    }
+
+    /// Copy, check & delete one page for `copy_then_delete_pages`, returning
+    /// the wiki's last answer and the steps taken
+    async fn copy_then_delete(&self, page: &PageListItem, to: &Destination, locale: &str) -> Result<(ResponseStatus, String), WikiError> {
+        let path = to.of(page);
+        let mut source = self.get_page(page.id).await?;
+        source.locale = locale.to_owned();
+
+        let (created, copy) = self.create_page(&source, &path).await?;
+        if !created.succeeded {
+            return Ok((created, "not copied".to_owned()));
+        }
+
+        // the wiki answers a create with the new page, so there's no need to look for it
+        let copy = match copy {
+            Some(id) => id,
+            None => return Ok((copy_unverified(&path), "copied, but the wiki didn't say which page the copy is".to_owned())),
+        };
+        match self.get_page(copy).await {
+            Ok(copied) if copied.content == source.content => {}
+            Ok(_) => {
+                let undo = self.delete_page(copy).await;
+                return Ok((copy_unverified(&path), format!("copied, but the copy's content differs, {}", copy_undone(undo))));
+            }
+            Err(e) => {
+                let undo = self.delete_page(copy).await;
+                return Ok((request_failed(page, &e), format!("copied, but checking the copy failed, {}", copy_undone(undo))));
+            }
+        }
+
+        let deleted = match self.delete_page(page.id).await {
+            Ok(deleted) => deleted,
+            // the wiki may have deleted the original without answering, so the copy is
+            // only deleted once the original is seen to still be there
+            Err(e) => {
+                let steps = match self.get_page(page.id).await {
+                    Ok(_) => copy_undone(self.delete_page(copy).await),
+                    Err(_) => "copy left in place, as the original may be gone",
+                };
+                return Ok((request_failed(page, &e), format!("copied, checked, deleting the original failed, {}", steps)));
+            }
+        };
+        if !deleted.succeeded {
+            let undo = self.delete_page(copy).await;
+            return Ok((deleted, format!("copied, checked, original not deleted, {}", copy_undone(undo))));
+        }
+        Ok((deleted, "copied, checked, original deleted".to_owned()))
+    }
+
+    /// Requests to have in flight for operations which don't ramp up with
+    /// `Concurrency::Auto`, which are sent its `max` at once
+    fn steady_concurrency(&self) -> usize {
+        match self.concurrency {
+            Concurrency::Fixed(limit) => limit.max(1),
+            Concurrency::Auto { max } => max.max(1),
+        }
+    }
+
+    /// Send the moves for `items`, `limit` at a time, until `stop` resolves.
+    /// 
+    /// Stopping ends the stream of new requests, buffer_unordered still drains those in flight.
+    /// Throttling the source caps throughput, while buffer_unordered caps requests in flight
+    async fn send_moves<'p, 'o, 'q: 'o>(
+        &self,
+        items: impl Iterator<Item = (&'o Operation<'q, MoveSinglePage>, &'p PageListItem)>,
+        limit: usize,
+        timeout: Option<Duration>,
+        stop: impl Future<Output = ()>,
+    ) -> Vec<MoveResponse<'p>> {
+        stream::iter(items)
+            .then(|item| self.throttle().map(move |_| item))
+            .take_until(stop)
+            .map(|(op, page)| {
+                let started = Instant::now();
+                self.send_move(op, timeout)
+                    .map(move |r| (page, r, started.elapsed()))
+            })
+            .buffer_unordered(limit)
+            .collect::<Vec<_>>()
+            .await
+    }
+
+    pub async fn get_page(&self, id: i32) -> Result<Page, WikiError> {
+        let op = GetSinglePage::build(
+            GetSinglePageArguments{id}
+        );
+        self.throttle().await;
+
+        match self.send(&op).await?.pages {
+            Some(spq) => match spq.single {
+                Some(p) => Ok(p),
+                None => Err(WikiError::NoData(format!("No page returned with id {}", id)))
+            },
+            None => Err(WikiError::NoData("No pages returned".to_owned()))
+        }
+    }
+
+    /// Create a new page at `path`, using `template` for the content & metadata.
+    /// Returns the ID the wiki gave the new page along with the status, if it was created
+    pub async fn create_page(&self, template: &Page, path: &str) -> Result<(ResponseStatus, Option<i32>), WikiError> {
+        let op = CreateSinglePage::build(
+            CreateSinglePageArguments{
+                content: template.content.clone(),
+                description: template.description.clone(),
+                editor: template.editor.clone(),
+                is_published: template.is_published,
+                is_private: template.is_private,
+                locale: template.locale.clone(),
+                path: path.to_owned(),
+                tags: template.tags.iter().flatten().map(|t| t.tag.clone()).collect(),
+                title: template.title.clone(),
+            }
+        );
+
+        match self.send(&op).await?.pages {
+            Some(pm) => match pm.create {
+                Some(pr) => Ok((pr.response_result, pr.page.map(|p| p.id))),
+                None => Err(WikiError::NoData("No create result returned".to_owned()))
+            },
+            None => Err(WikiError::NoData("No pages returned".to_owned()))
+        }
+    }
+
+    /// Replace the content of page `id` with that of `template`
+    pub async fn update_page(&self, id: i32, template: &Page) -> Result<ResponseStatus, WikiError> {
+        let op = UpdateSinglePage::build(
+            UpdateSinglePageArguments{
+                id,
+                content: template.content.clone(),
+                description: Some(template.description.clone()),
+                tags: Some(template.tags.iter().flatten().map(|t| Some(t.tag.clone())).collect()),
+                title: Some(template.title.clone()),
+            }
+        );
+
+        match self.send(&op).await?.pages {
+            Some(pm) => match pm.update {
+                Some(pr) => Ok(pr.response_result),
+                None => Err(WikiError::NoData("No update result returned".to_owned()))
+            },
+            None => Err(WikiError::NoData("No pages returned".to_owned()))
+        }
+    }
 
-        let raw_responses = join_all(requests).await;
+    /// Every tag in the wiki, whether or not any page uses it
+    pub async fn list_tags(&self) -> Result<Vec<PageTag>, WikiError> {
+        let op = ListTags::build(());
 
-        let (ok, err): (Vec<_>, Vec<_>) = raw_responses.into_iter().partition(|r|r.is_ok());
+        match self.send(&op).await?.pages {
+            Some(tq) => Ok(tq.tags.into_iter().flatten().collect()),
+            None => Err(WikiError::NoData("No pages returned".to_owned()))
+        }
+    }
+
+    /// Versions of page `id`, newest first
+    pub async fn get_page_history(&self, id: i32) -> Result<PageHistoryResult, WikiError> {
+        let op = ListPageHistory::build(
+            ListPageHistoryArguments{id}
+        );
 
-        match err.len() {
-            0 => {} // no errors
-            _ => {match ok.len() {
-                0 => {bail!("All the requests failed.");}, // all errors
-                _ => {bail!("Some, but not all, requests failed. The move may be partially complete.");} 
-            }}
+        match self.send(&op).await?.pages {
+            Some(phq) => match phq.history {
+                Some(h) => Ok(h),
+                None => Err(WikiError::NoData(format!("No history returned for page {}", id)))
+            },
+            None => Err(WikiError::NoData("No pages returned".to_owned()))
         }
+    }
+
+    /// Restore page `page_id` to the content it had at `version_id`
+    pub async fn restore_page(&self, page_id: i32, version_id: i32) -> Result<ResponseStatus, WikiError> {
+        let op = RestorePageVersion::build(
+            RestorePageVersionArguments{page_id, version_id}
+        );
 
-        let jsons = join_all(ok.into_iter()
-            .map(|r| r.expect("unreachable").json::<cynic::GraphQlResponse<Value>>())).await;
+        match self.send(&op).await?.pages {
+            Some(pm) => match pm.restore.and_then(|dr| dr.response_result) {
+                Some(rs) => Ok(rs),
+                None => Err(WikiError::NoData("No restore result returned".to_owned()))
+            },
+            None => Err(WikiError::NoData("No pages returned".to_owned()))
+        }
+    }
 
-        let (ok, err): (Vec<_>, Vec<_>) = jsons.into_iter().partition(|r|r.is_ok());
+    /// Delete each page, as many at once as moves, collecting the pages the wiki refused.
+    /// 
+    /// If requests fail after some pages were deleted, the error is a
+    /// `WikiError::PartialDelete` saying what was done.
+    pub async fn delete_pages(&self, pages: &[queries::PageListItem]) -> Result<DeleteSuccess, WikiError> {
+        // generate an op for each page
+        let ops = pages
+            .iter()
+            .map(|p| DeleteSinglePage::build(DeleteSinglePageArguments{id: p.id}))
+            .collect::<Vec<_>>();
 
-        match err.len() {
-            0 => {} // no errors
-            _ => {match ok.len() {
-                0 => {bail!("Deserialising JSON from all responses failed.");}, // all errors
-                _ => {bail!("Deserialising JSON from some responses failed. The move may be partially complete.");} 
-            }}
+        if let Some(op) = ops.first() {
+            self.dump(op);
         }
 
-        let (ok, err): (Vec<_>, Vec<_>) = ok.into_iter()
-            .zip(ops)
-            .filter_map(|(j, op)| {
-                let tag = op.decode_response(j.unwrap()).unwrap().data;
-                
-                match tag {
-                    Some(t) => match t.pages {
-                            Some(ptm) => match ptm.move_ {
-                                Some(dr) => dr.response_result,
-                                None => None
-                            },
-                            None => None
+        let responses = stream::iter(ops.iter().zip(pages))
+            .then(|item| self.throttle().map(move |_| item))
+            .map(|(op, page)| self.send_delete(op).map(move |r| (page, r)))
+            .buffer_unordered(self.steady_concurrency())
+            .collect::<Vec<_>>()
+            .await;
+
+        let mut deleted = Vec::new();
+        let mut failures = Vec::new();
+        let mut errors = Vec::new();
+        for (page, response) in responses {
+            match response {
+                Ok(rs) if rs.succeeded => deleted.push(page.id),
+                Ok(rs) => failures.push(rs),
+                Err(e) => errors.push(e),
+            }
+        }
+
+        let delete_success = DeleteSuccess{
+            success_count: deleted.len(),
+            failures: match failures.len() {0 => None, _ => Some(failures)},
+            deleted,
+        };
+
+        match (errors.len(), delete_success.success_count, &delete_success.failures) {
+            (0, _, _) => Ok(delete_success), // no errors
+            (_, 0, None) => Err(errors.remove(0)), // all errors
+            _ => Err(WikiError::PartialDelete(delete_success)),
+        }
+    }
+
+    /// Delete page `id`
+    pub async fn delete_page(&self, id: i32) -> Result<ResponseStatus, WikiError> {
+        self.send_delete(&DeleteSinglePage::build(DeleteSinglePageArguments{id})).await
+    }
+
+    async fn send_delete(&self, op: &Operation<'_, DeleteSinglePage>) -> Result<ResponseStatus, WikiError> {
+        match self.send(op).await?.pages {
+            Some(pm) => match pm.delete.and_then(|dr| dr.response_result) {
+                Some(rs) => Ok(rs),
+                None => Err(WikiError::NoData("No delete result returned".to_owned()))
+            },
+            None => Err(WikiError::NoData("No pages returned".to_owned()))
+        }
+    }
+
+    /// Replace the content of page `id`, keeping its title, description & tags
+    pub async fn update_page_content(&self, id: i32, content: String) -> Result<ResponseStatus, WikiError> {
+        let page = Page { content, ..self.get_page(id).await? };
+        self.update_page(id, &page).await
+    }
+
+    /// Copy each page to `destination`, replacing `prefix`, as a folder if `as_folder`.
+    /// 
+    /// Pages already existing at the destination are skipped, unless `overwrite`
+    /// is set, in which case their content is replaced. No more pages are copied
+    /// at once than the configured concurrency allows.
+    pub async fn copy_pages(
+        &self, 
+        pages: &[queries::PageListItem], 
+        prefix: &str, 
+        destination: &str,
+        as_folder: bool,
+        overwrite: bool,
+    ) -> Result<CopySuccess, WikiError> {
+
+        let existing = self.list_pages(destination, None).await?.pages;
+
+        let copies = pages
+            .iter()
+            .map(|p| {
+                let destination_path = compute_destination(p, prefix, destination, as_folder);
+                let existing_id = existing
+                    .iter()
+                    .find(|e| e.path == destination_path)
+                    .map(|e| e.id);
+                async move {
+                    match (existing_id, overwrite) {
+                        (Some(_), false) => Ok(None),
+                        (Some(id), true) => {
+                            let page = self.get_page(p.id).await?;
+                            self.update_page(id, &page).await.map(Some)
+                        },
+                        (None, _) => {
+                            let page = self.get_page(p.id).await?;
+                            self.create_page(&page, &destination_path).await.map(|(rs, _)| Some(rs))
                         }
-                    None => None
-                    }     
-                })
+                    }
+                }
+            });
+
+        // in order, so each result still lines up with its page below
+        let results = stream::iter(copies)
+            .buffered(self.steady_concurrency())
+            .collect::<Vec<_>>()
+            .await;
+
+        let mut skipped = Vec::new();
+        let mut done = Vec::new();
+        let mut errors = Vec::new();
+        for (result, p) in results.into_iter().zip(pages) {
+            match result {
+                Ok(None) => skipped.push(compute_destination(p, prefix, destination, as_folder)),
+                Ok(Some(rs)) => done.push(rs),
+                Err(e) => errors.push(e),
+            }
+        }
+
+        let (ok, err): (Vec<_>, Vec<_>) = done.into_iter()
             .partition(|r| r.succeeded);
 
-        Ok(MoveSuccess{
-            success_count: ok.len(), 
+        let copy_success = CopySuccess{
+            success_count: ok.len(),
+            skipped,
             failures: match err.len() {0 => None, _ => Some(err)}
-         })
+        };
+
+        match (errors.len(), copy_success.success_count, &copy_success.failures) {
+            (0, _, _) => Ok(copy_success), // no errors
+            (_, 0, None) if copy_success.skipped.is_empty() => Err(errors.remove(0)), // all errors
+            _ => Err(WikiError::PartialCopy(copy_success)),
+        }
     }
 
+    /// List pages beginning with `prefix` which no other page links to, going by the links
+    /// in the configured locale
+    pub async fn list_orphaned_pages(&self, prefix: &str) -> Result<ListPages, WikiError> {
+        let locale = self.locale.as_str();
+        let op = ListPageLinks::build(
+            ListPageLinksArguments{locale: locale.to_owned()}
+        );
 
-    /// Check that no pages being moved have `/private/` in the path or `private` tag
-    pub async fn safety_check_private<'a>(&self, pages: impl Iterator<Item = &'a PageListItem>)
-    -> Option<impl Iterator<Item = &'a PageListItem>> {
-        let private_word = "private";
-        let private_tag = Some(private_word.to_string());
+        // Older servers don't know about `pages.links` at all
+        let response = match self.send(&op).await {
+            Err(WikiError::GraphQl(messages)) if messages.iter().any(|m| m.contains("links")) => {
+                return Err(WikiError::Unsupported(format!(
+                    "Finding orphaned pages requires a Wiki.js version which supports `pages.links`. The server said: {}",
+                    messages.join(", ")
+                )))
+            }
+            response => response?,
+        };
 
-        let mut private_pages = pages
-            .filter(move |p| {
-                let is_private_tag = match &p.tags {
-                    Some(tags) => tags.contains(&private_tag),
-                    None => false
-                };
-                let is_private_path = p.path.contains(&private_word);
-                is_private_tag || is_private_path
+        let link_list = match response.pages {
+            Some(plq) => match plq.links {
+                Some(l) => l,
+                None => return Err(WikiError::NoData("No links returned".to_owned()))
+            },
+            None => return Err(WikiError::NoData("No pages returned".to_owned()))
+        };
+
+        let linked = link_list
+            .iter()
+            .flatten()
+            .flat_map(|l| l.links.iter().flatten())
+            .filter_map(|l| l.strip_prefix(locale)?.strip_prefix('/'))
+            .collect::<std::collections::HashSet<_>>();
+
+        let ListPages { pages, pages_returned } = self.list_pages(prefix, None).await?;
+
+        let orphans = pages
+            .into_iter()
+            .filter(|p| !linked.contains(p.path.as_str()))
+            .collect();
+
+        Ok(ListPages{ pages: orphans, pages_returned })
+    }
+
+    /// Find the pages being moved or deleted which look private, going by the configured
+    /// sensitive markers. See `is_private`
+    pub fn safety_check_private<'a>(&self, pages: impl Iterator<Item = &'a PageListItem>) -> Vec<&'a PageListItem> {
+        pages
+            .filter(|p| is_private(p, &self.sensitive_markers))
+            .collect()
+    }
+
+    /// Fetch the content of each page and find those containing `PRIVATE_MARKER`.
+    /// 
+    /// This costs a request per page, so run the cheap `safety_check_private` first
+    /// and only deep scan the pages it didn't flag.
+    pub async fn safety_check_private_deep<'a>(&self, pages: impl Iterator<Item = &'a PageListItem>)
+    -> Result<Vec<&'a PageListItem>, WikiError> {
+        let found = self.pages_containing(pages, PRIVATE_MARKER).await?;
+        Ok(found.into_iter().map(|(p, _)| p).collect())
+    }
+
+    /// Fetch each moved page again and find those the wiki doesn't have at their
+    /// destination yet, along with the path it does have. Costs a request per page.
+    pub async fn verify_moves<'a>(
+        &self,
+        pages: impl Iterator<Item = &'a PageListItem>,
+        to: &Destination,
+    ) -> Result<Vec<(&'a PageListItem, String)>, WikiError> {
+        let checked = futures::stream::iter(pages)
+            .map(|p| async move {
+                let page = self.get_page(p.id).await?;
+                let landed = page.path == to.of(p);
+                Ok::<_, WikiError>((!landed).then_some((p, page.path)))
             })
-            .peekable();
+            .buffered(DEEP_SCAN_CONCURRENCY)
+            .try_collect::<Vec<_>>()
+            .await?;
 
-        match private_pages.peek().is_some() {
-            true => Some(private_pages),
-            false => None
-        } 
+        Ok(checked.into_iter().flatten().collect())
     }
-}
\ No newline at end of file
+
+    /// Fetch up to `METADATA_SAMPLE` of the moved pages, picked at random, and
+    /// compare them with their listing from before the move.
+    pub async fn metadata_changes<'a>(&self, pages: impl Iterator<Item = &'a PageListItem>)
+    -> Result<Vec<MetadataChanges<'a>>, WikiError> {
+        let sample = fastrand::choose_multiple(pages, METADATA_SAMPLE);
+        futures::stream::iter(sample)
+            .map(|p| async move {
+                let page = self.get_page(p.id).await?;
+                let tags_before = p.tags.iter().flatten().flatten().map(String::as_str).sorted().collect::<Vec<_>>();
+                let tags_after = page.tags.iter().flatten().map(|t| t.tag.as_str()).sorted().collect::<Vec<_>>();
+                Ok(MetadataChanges {
+                    page: p,
+                    locale: (page.locale != p.locale).then(|| page.locale.clone()),
+                    title: p.title.as_deref().unwrap_or_default() != page.title,
+                    tags: tags_before != tags_after,
+                    updated_at: page.updated_at != p.updated_at,
+                })
+            })
+            .buffered(DEEP_SCAN_CONCURRENCY)
+            .try_collect()
+            .await
+    }
+
+    /// Fetch the content of each page and keep those containing `needle`,
+    /// along with the first line it appears on. Costs a request per page.
+    pub async fn pages_containing<'a>(&self, pages: impl Iterator<Item = &'a PageListItem>, needle: &str)
+    -> Result<Vec<(&'a PageListItem, String)>, WikiError> {
+        self.pages_where(pages, |content| {
+            content.lines().find(|l| l.contains(needle)).map(|l| l.trim().to_owned())
+        }).await
+    }
+
+    /// Fetch the content of each page and keep those `find` picks a line out of,
+    /// along with that line. Costs a request per page.
+    pub async fn pages_where<'a>(
+        &self,
+        pages: impl Iterator<Item = &'a PageListItem>,
+        find: impl Fn(&str) -> Option<String>,
+    ) -> Result<Vec<(&'a PageListItem, String)>, WikiError> {
+        let find = &find;
+        let scanned = futures::stream::iter(pages)
+            .map(|p| async move {
+                let page = self.get_page(p.id).await?;
+                Ok::<_, WikiError>(find(&page.content).map(|line| (p, line)))
+            })
+            .buffered(DEEP_SCAN_CONCURRENCY)
+            .try_collect::<Vec<_>>()
+            .await?;
+
+        Ok(scanned.into_iter().flatten().collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+    use cynic::serde_json::{json, Value};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    /// A wiki answering over HTTP/1.1 on a local port, with the status & body `answer`
+    /// gives each request's query & variables. One request per connection
+    async fn fake_wiki(answer: impl Fn(&str, &Value) -> (u16, String) + Send + Sync + 'static) -> Wiki {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let endpoint = format!("http://{}/graphql", listener.local_addr().unwrap());
+        let answer = Arc::new(answer);
+        tokio::spawn(async move {
+            loop {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let answer = answer.clone();
+                tokio::spawn(async move {
+                    let mut request = Vec::new();
+                    let mut buf = [0; 4096];
+                    let body = loop {
+                        let n = socket.read(&mut buf).await.unwrap();
+                        request.extend_from_slice(&buf[..n]);
+                        let text = String::from_utf8_lossy(&request);
+                        if let Some((head, body)) = text.split_once("\r\n\r\n") {
+                            let length = head.lines()
+                                .find_map(|l| l.to_ascii_lowercase().strip_prefix("content-length:").map(|n| n.trim().parse::<usize>().unwrap()))
+                                .unwrap_or(0);
+                            if body.len() >= length {
+                                break body.to_owned();
+                            }
+                        }
+                    };
+                    let request: Value = cynic::serde_json::from_str(&body).unwrap();
+                    let (status, response) = answer(request["query"].as_str().unwrap(), &request["variables"]);
+                    let reply = format!(
+                        "HTTP/1.1 {} X\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                        status, response.len(), response
+                    );
+                    socket.write_all(reply.as_bytes()).await.unwrap();
+                });
+            }
+        });
+        Wiki::builder().endpoint(endpoint).api_key("key").https(false).http2(false).build().unwrap()
+    }
+
+    /// The id a query's variables give, if any
+    fn id_of(variables: &Value) -> Option<i64> {
+        variables.as_object()?.values().find_map(Value::as_i64)
+    }
+
+    fn ok() -> Value {
+        json!({ "succeeded": true, "errorCode": 0, "slug": "ok", "message": null })
+    }
+
+    /// Page 1 at `docs/a`, copied to page 99. Requests `failing` picks out get a 502,
+    /// and the ids of pages deleted are kept in the returned list
+    async fn copying_wiki(failing: fn(&str, Option<i64>) -> bool) -> (Wiki, Arc<Mutex<Vec<i64>>>) {
+        let deleted = Arc::new(Mutex::new(Vec::new()));
+        let kept = deleted.clone();
+        let wiki = fake_wiki(move |query, variables| {
+            let id = id_of(variables);
+            if failing(query, id) {
+                return (502, String::new());
+            }
+            let data = if query.contains("single") {
+                json!({ "pages": { "single": {
+                    "path": "docs/a", "title": "A", "description": "", "content": "hello", "editor": "markdown",
+                    "locale": "en", "isPrivate": false, "isPublished": true, "tags": [], "updatedAt": "2022-01-01T00:00:00Z"
+                } } })
+            } else if query.contains("create") {
+                json!({ "pages": { "create": { "responseResult": ok(), "page": { "id": 99 } } } })
+            } else if query.contains("delete") {
+                kept.lock().unwrap().push(id.unwrap());
+                json!({ "pages": { "delete": { "responseResult": ok() } } })
+            } else {
+                json!(null)
+            };
+            (200, json!({ "data": data }).to_string())
+        }).await;
+        (wiki, deleted)
+    }
+
+    fn page() -> PageListItem {
+        PageListItem {
+            id: 1,
+            path: "docs/a".to_owned(),
+            locale: "en".to_owned(),
+            tags: None,
+            title: Some("A".to_owned()),
+            content_type: "markdown".to_owned(),
+            created_at: chrono::DateTime::default(),
+            updated_at: chrono::DateTime::default(),
+        }
+    }
+
+    #[tokio::test]
+    async fn a_body_which_isnt_json_is_a_decode_error() {
+        let wiki = fake_wiki(|_, _| (200, "<html>Under maintenance</html>".to_owned())).await;
+
+        assert!(matches!(wiki.get_wiki_title().await, Err(WikiError::Decode(_))));
+    }
+
+    #[tokio::test]
+    async fn a_network_error_says_what_went_wrong() {
+        // bound then dropped, so nothing is listening on it
+        let port = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        let wiki = Wiki::builder()
+            .endpoint(format!("http://127.0.0.1:{}/graphql", port))
+            .api_key("key")
+            .https(false)
+            .http2(false)
+            .build()
+            .unwrap();
+
+        let e = wiki.get_wiki_title().await.unwrap_err();
+        let message = e.to_string();
+        let reason = match &e {
+            WikiError::Network(source) => source.to_string(),
+            _ => panic!("expected a network error, got {:?}", e),
+        };
+        assert!(message.starts_with("Failed to reach the wiki at http://127.0.0.1"), "{}", message);
+        assert!(message.ends_with(&reason), "{}", message);
+    }
+
+    fn archive() -> Destination {
+        Destination::Prefix { prefix: "docs".to_owned(), destination: "archive".to_owned(), as_folder: false }
+    }
+
+    #[tokio::test]
+    async fn a_copy_which_cant_be_checked_is_deleted_again() {
+        let (wiki, deleted) = copying_wiki(|query, id| query.contains("single") && id == Some(99)).await;
+        let to = archive();
+
+        let moves = wiki.copy_then_delete_pages(&[page()], &to, None, std::future::pending()).await.unwrap();
+
+        assert_eq!(moves.success_count, 0);
+        assert_eq!(moves.failures.unwrap()[0].slug, "RequestFailed");
+        assert_eq!(*deleted.lock().unwrap(), vec![99]);
+    }
+
+    #[tokio::test]
+    async fn a_copy_is_deleted_again_when_deleting_the_original_fails() {
+        let (wiki, deleted) = copying_wiki(|query, id| query.contains("delete") && id == Some(1)).await;
+        let to = archive();
+
+        let moves = wiki.copy_then_delete_pages(&[page()], &to, None, std::future::pending()).await.unwrap();
+
+        assert_eq!(moves.success_count, 0);
+        assert_eq!(moves.failures.unwrap()[0].slug, "RequestFailed");
+        assert_eq!(*deleted.lock().unwrap(), vec![99]);
+        assert_eq!(moves.outcomes[0].steps.as_deref(), Some("copied, checked, deleting the original failed, copy deleted again"));
+    }
+}
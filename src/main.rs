@@ -1,21 +1,30 @@
-use anyhow::{bail, Result};
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Utc};
 use clap::{ArgEnum, Args, Parser, Subcommand};
 use console::{Emoji, Term};
 use cynic::serde::{Serialize, Deserialize};
+use cynic::serde_json::{json, Value};
 use dialoguer::Confirm;
-use enable_ansi_support;
-use human_panic;
+use futures::TryStreamExt;
 use itertools::Itertools;
+use std::time::Duration;
 use owo_colors::colors::*;
 use owo_colors::{OwoColorize, Stream, Style};
-use confy;
+use regex::Regex;
 
-mod lib;
-use lib::Wiki;
+use wiki::{Wiki, WikiConfig, WikiError, ListAllPagesArguments, ListFields, ListPages, MoveSuccess, PageListItem, PRIVATE_MARKER, SUPPORTED_MAJOR_VERSION};
+use wiki::{ApiVersion, PageOrderBy, PageOrderByDirection, Concurrency, Destination, PathTransform, ResponseStatus, DEFAULT_LOCALE, DEFAULT_SENSITIVE_MARKERS, is_supported_version};
 
 /// A very simple utility for bulk operations on Wiki pages.
 #[derive(Debug, Parser)]
 #[clap(name = "wikcli", version = "0.1.0", author = "Angel~👼")]
+#[clap(after_help = "EXIT CODES:
+    0    Success
+    1    Configuration or usage error
+    2    Partial failure, some pages were not moved/copied/deleted
+    3    Failure, no pages were moved/copied/deleted
+    4    Aborted by the user
+    5    Private pages found, with move or delete --fail-on-private")]
 pub struct App {
     #[clap(flatten)]
     global_opts: GlobalOpts,
@@ -29,14 +38,227 @@ enum Command {
     /// List wiki pages by path prefix
     List {
         /// Path prefix   
-        path: String,
+        #[clap(required_unless_present = "prefix-file", conflicts_with = "prefix-file")]
+        path: Option<String>,
+
+        /// File of path prefixes, one per line. Pages beginning with any of them are listed
+        #[clap(long, parse(from_os_str))]
+        prefix_file: Option<std::path::PathBuf>,
 
-        // Filter by Tags
+        /// Only pages with this tag. Repeat it for several, which pages must all have unless --tag-mode any
         #[clap(long, short = 't')]
         tags: Option<Vec<String>>,
+
+        /// Only pages updated at or after this RFC 3339 timestamp, e.g. 2022-01-31T00:00:00Z
+        #[clap(long, parse(try_from_str = parse_timestamp))]
+        since: Option<DateTime<Utc>>,
+
+        /// Only pages written with this editor type: markdown or html (visual, raw HTML & AsciiDoc)
+        #[clap(long)]
+        editor: Option<String>,
+
+        /// Only pages without a title, or with a blank one
+        #[clap(long)]
+        only_untitled: bool,
+
+        /// Only pages in this locale, e.g. en. The global --locale does the same here
+        #[clap(long)]
+        locale_filter: Option<String>,
+
+        /// Only pages last edited by the user with this ID
+        #[clap(long, value_name = "USER_ID")]
+        author: Option<i32>,
+
+        /// Only pages created by the user with this ID
+        #[clap(long, value_name = "USER_ID")]
+        creator: Option<i32>,
+
+        /// Only pages whose content contains this text. Fetches every page under the prefix, so can be slow
+        #[clap(long, conflicts_with = "no-sort")]
+        content_contains: Option<String>,
+
+        /// Only pages with a line of content matching this regex. With --content-contains, pages must match both
+        #[clap(long, value_name = "REGEX", parse(try_from_str = Regex::new), conflicts_with = "no-sort")]
+        content_regex: Option<Regex>,
+
+        /// Only print the number of matching pages (and, with -v, the number returned by wiki)
+        #[clap(long)]
+        count: bool,
+
+        /// Order pages by path [default], by how deeply nested they are (then by path), or by title,
+        /// id, created or updated date. All but depth are ordered by the wiki
+        #[clap(long = "order-by", alias = "sort", arg_enum, conflicts_with = "no-sort")]
+        order_by: Option<PageSort>,
+
+        /// Ascending [default] or descending order
+        #[clap(long, arg_enum, conflicts_with = "no-sort")]
+        direction: Option<Direction>,
+
+        /// Reverse the order, e.g. to put the most deeply nested pages first. Same as --direction desc
+        #[clap(long, conflicts_with_all = &["no-sort", "direction"])]
+        reverse: bool,
+
+        /// Only the first N pages, in order. The wiki does the limiting when it orders the pages
+        /// and there's nothing to filter by here, such as a prefix
+        #[clap(long, value_name = "N", conflicts_with = "no-sort")]
+        limit: Option<usize>,
+
+        /// Skip the first N pages, in order, e.g. to page through with --limit. Always done here,
+        /// as the wiki can't skip pages
+        #[clap(long, value_name = "N", conflicts_with = "no-sort")]
+        offset: Option<usize>,
+
+        /// List pages under a heading for each of their tags. A page with several tags is listed under each
+        #[clap(long, conflicts_with_all = &["no-sort", "count"])]
+        group_by_tag: bool,
+
+        /// Print pages as they arrive, in the order the wiki returns them, with full paths
+        #[clap(long, conflicts_with = "count")]
+        no_sort: bool,
+
+        /// List the pages, then every this many seconds list those added, removed or changed since. Ctrl-C stops
+        #[clap(
+            long,
+            value_name = "SECONDS",
+            parse(try_from_str = parse_seconds),
+            conflicts_with_all = &["no-sort", "count", "group-by-tag", "content-contains", "content-regex"]
+        )]
+        watch: Option<Duration>,
+
+        /// Columns to list, in order, e.g. id,path,updated. Paths are printed in full
+        #[clap(long, arg_enum, use_value_delimiter = true, conflicts_with = "count")]
+        fields: Option<Vec<Field>>,
     },
     /// Move wiki pages to a new path
     Move {
+        /// Path prefix
+        #[clap(required_unless_present = "plan-file")]
+        path: Option<String>,
+
+        /// Destination to replace prefix
+        #[clap(long, short = 'd', required_unless_present_any = &["path-transform", "plan-file"])]
+        destination: Option<String>,
+
+        /// Work out each destination by substituting in the page's whole path, sed-style,
+        /// e.g. 's/^docs\/(api|sdk)/docs\/v2\/$1/'. The old text is a regex, and $1 puts back what its first group matched
+        #[clap(long, conflicts_with_all = &["destination", "nest"])]
+        path_transform: Option<PathTransform>,
+
+        /// Only pages with this tag. Repeat it for several, which pages must all have unless --tag-mode any
+        #[clap(long, short = 't')]
+        tags: Option<Vec<String>>,
+
+        /// Only move pages in this locale, e.g. en
+        #[clap(long, alias = "locale-filter")]
+        source_locale: Option<String>,
+
+        /// Move the pages into this locale. Changing a page's locale must be confirmed, even with --yes [default: --locale, or en]
+        #[clap(long)]
+        dest_locale: Option<String>,
+
+        /// Move the pages named on stdin, one ID or path per line, instead of searching by prefix.
+        /// They must all begin with the path prefix, unless --nest is given
+        #[clap(long, conflicts_with = "tags")]
+        from_stdin: bool,
+
+        /// Keep each page's whole path under the destination, e.g. docs/a -> archive/docs/a
+        #[clap(long, alias = "append")]
+        nest: bool,
+
+        /// Treat the destination as a folder, joining it to the rest of each path with one slash [default]
+        #[clap(long, conflicts_with = "as-prefix")]
+        as_folder: bool,
+
+        /// Join the destination to the rest of each path as they are, e.g. with -d b, a/x -> bx for the prefix a/
+        #[clap(long, conflicts_with = "path-transform")]
+        as_prefix: bool,
+
+        /// Move pages one at a time, stopping at the first failure
+        #[clap(long)]
+        fail_fast: bool,
+
+        /// Give up on a page if its move isn't sent within this many seconds
+        #[clap(long, parse(try_from_str = parse_timeout))]
+        timeout_per_page: Option<Duration>,
+
+        /// Rather than the wiki's move, copy each page, check the copy, then delete the original.
+        /// A copy is deleted again if that fails. Moved pages get new IDs
+        #[clap(long, conflicts_with_all = &["fail-fast", "timeout-per-page", "verify"])]
+        copy_then_delete: bool,
+
+        /// If some pages fail to move, move those which did back to where they were
+        #[clap(long, conflicts_with = "copy-then-delete")]
+        rollback_on_partial_failure: bool,
+
+        /// Don't ask for confirmation. Private pages must still be confirmed
+        #[clap(long, short = 'y')]
+        yes: bool,
+
+        /// Go ahead even when destination paths collide, or more than --max-pages pages match
+        #[clap(long)]
+        force: bool,
+
+        /// Refuse to move more than this many pages without --force [default: 100]
+        #[clap(long)]
+        max_pages: Option<usize>,
+
+        /// Don't ask for confirmation when fewer than this many pages move. Private pages must still be confirmed [default: 0]
+        #[clap(long)]
+        confirm_threshold: Option<usize>,
+
+        /// Pick which of the matching pages to move from a list
+        #[clap(long, short = 's')]
+        select: bool,
+
+        /// Don't list every page before moving, just how many will move and where to.
+        /// Private pages are still listed
+        #[clap(long)]
+        summary_only: bool,
+
+        /// Also fetch every page's content and check it for a `<!-- private -->` marker. Slow!
+        #[clap(long)]
+        deep_scan: bool,
+
+        /// Don't check for private pages at all. For automated jobs, so needs --yes
+        #[clap(long, requires = "yes", conflicts_with = "deep-scan")]
+        no_safety_check: bool,
+
+        /// Stop with exit code 5, rather than asking, if any page looks private. For automated jobs
+        #[clap(long, conflicts_with = "no-safety-check")]
+        fail_on_private: bool,
+
+        /// Afterwards, fetch each moved page again to check it's at its destination. Doubles the requests
+        #[clap(long)]
+        verify: bool,
+
+        /// Write the outcome of every page's move to this file as JSON
+        #[clap(long, parse(from_os_str))]
+        report_file: Option<std::path::PathBuf>,
+
+        /// Afterwards, print every page's ID, old & new path and outcome
+        #[clap(long)]
+        audit: bool,
+
+        /// Also move system pages, such as `home`, which are otherwise left where they are
+        #[clap(long)]
+        include_system_pages: bool,
+
+        /// Show what would be moved, and stop before moving anything.
+        /// With --format json, print the plan of moves for --plan-file instead
+        #[clap(long)]
+        dry_run: bool,
+
+        /// Move exactly the pages in a plan printed by --dry-run --format json, without listing them again
+        #[clap(
+            long,
+            parse(from_os_str),
+            conflicts_with_all = &["path", "destination", "path-transform", "tags", "source-locale", "from-stdin", "nest", "as-prefix", "select"]
+        )]
+        plan_file: Option<std::path::PathBuf>,
+    },
+
+    /// Copy wiki pages to a new path, leaving the originals in place
+    Copy {
         /// Path prefix
         path: String,
 
@@ -44,11 +266,150 @@ enum Command {
         #[clap(long, short = 'd')]
         destination: String,
 
-        // Filter by Tags
+        /// Only pages with this tag. Repeat it for several, which pages must all have unless --tag-mode any
+        #[clap(long, short = 't')]
+        tags: Option<Vec<String>>,
+
+        /// Overwrite pages which already exist at the destination, rather than skipping them
+        #[clap(long)]
+        overwrite: bool,
+
+        /// Treat the destination as a folder, joining it to the rest of each path with one slash [default]
+        #[clap(long, conflicts_with = "as-prefix")]
+        as_folder: bool,
+
+        /// Join the destination to the rest of each path as they are, e.g. with -d b, a/x -> bx for the prefix a/
+        #[clap(long)]
+        as_prefix: bool,
+    },
+
+    /// Delete wiki pages by path prefix. Private pages must be confirmed a second time
+    Delete {
+        /// Path prefix
+        path: String,
+
+        /// Only pages with this tag. Repeat it for several, which pages must all have unless --tag-mode any
+        #[clap(long, short = 't')]
+        tags: Option<Vec<String>>,
+
+        /// Don't ask for confirmation. Private pages must still be confirmed
+        #[clap(long, short = 'y')]
+        yes: bool,
+
+        /// Go ahead even when more than --max-pages pages match
+        #[clap(long)]
+        force: bool,
+
+        /// Refuse to delete more than this many pages without --force [default: 100]
+        #[clap(long)]
+        max_pages: Option<usize>,
+
+        /// Also delete system pages, such as `home`, which are otherwise left alone
+        #[clap(long)]
+        include_system_pages: bool,
+
+        /// List the pages which would be deleted, and which look private, without deleting anything
+        #[clap(long)]
+        dry_run: bool,
+
+        /// Don't check for private pages at all. For automated jobs, so needs --yes
+        #[clap(long, requires = "yes")]
+        no_safety_check: bool,
+
+        /// Stop with exit code 5, rather than asking, if any page looks private. For automated jobs
+        #[clap(long, conflicts_with = "no-safety-check")]
+        fail_on_private: bool,
+    },
+
+    /// Move wiki pages for each `prefix,destination` line of a CSV or TSV file
+    Batch {
+        /// File of source prefix & destination pairs, one per line
+        #[clap(parse(from_os_str))]
+        file: std::path::PathBuf,
+
+        /// Abort if any line is malformed, rather than skipping it
+        #[clap(long)]
+        strict: bool,
+
+        /// Don't ask for confirmation. Private pages must still be confirmed
+        #[clap(long, short = 'y')]
+        yes: bool,
+
+        /// Move more pages than --max-pages
+        #[clap(long)]
+        force: bool,
+
+        /// Refuse to move more than this many pages in all without --force [default: 100]
+        #[clap(long)]
+        max_pages: Option<usize>,
+
+        /// Also move system pages, such as `home`, which are otherwise left where they are
+        #[clap(long)]
+        include_system_pages: bool,
+    },
+
+    /// Summarise wiki pages by path prefix: totals by editor & tag, and untitled pages
+    Stats {
+        /// Path prefix
+        #[clap(default_value = "")]
+        path: String,
+
+        /// Only pages with this tag. Repeat it for several, which pages must all have unless --tag-mode any
         #[clap(long, short = 't')]
         tags: Option<Vec<String>>,
     },
 
+    /// List wiki pages by path prefix which no other page links to
+    Orphans {
+        /// Path prefix
+        #[clap(default_value = "")]
+        path: String,
+    },
+
+    /// Replace the content of a wiki page
+    Edit {
+        /// Page ID
+        id: i32,
+
+        /// File containing the new content. Reads stdin if omitted or `-`
+        #[clap(parse(from_os_str))]
+        file: Option<std::path::PathBuf>,
+    },
+
+    /// List every tag in the wiki, with how many pages use it
+    Tags {
+        /// Sort by tag name, or by most used
+        #[clap(long, arg_enum, default_value_t = TagSort::Name)]
+        sort: TagSort,
+    },
+
+    /// List the versions of a wiki page
+    History {
+        /// Page ID
+        id: i32,
+    },
+
+    /// Restore a wiki page to a previous version
+    Restore {
+        /// Page ID
+        id: i32,
+
+        /// Version ID, as shown by `history`
+        version: i32,
+
+        /// Don't ask for confirmation
+        #[clap(long, short = 'y')]
+        yes: bool,
+    },
+
+    /// Time lightweight requests to the wiki, to help pick --concurrency & --rate for a big move.
+    /// Sends --concurrency requests at once, and changes nothing
+    Bench {
+        /// How many requests to send
+        #[clap(long, short = 'n', default_value_t = 100)]
+        requests: usize,
+    },
+
     /// Generate config file
     Config {
         /// Interactive mode
@@ -58,7 +419,19 @@ enum Command {
         /// File location
         #[clap(long, short = 'o', parse(from_os_str))]
         output: Option<std::path::PathBuf>,
-    }
+
+        /// Test the connection before writing the config file
+        #[clap(long)]
+        verify: bool,
+
+        /// Change just this setting in the existing config file, e.g. endpoint=https://wiki.example.com. Leave the value empty to unset it
+        #[clap(long, value_name = "KEY=VALUE", conflicts_with = "interactive")]
+        set: Vec<String>,
+    },
+
+    /// Print the settings in effect after combining flags & the config file
+    #[clap(alias = "config-check")]
+    ShowConfig,
 }
 
 #[derive(Debug, Args)]
@@ -67,10 +440,34 @@ struct GlobalOpts {
     #[clap(long, arg_enum, global = true, default_value_t = Color::Auto)]
     color: Color,
 
+    /// Output format
+    #[clap(long, arg_enum, global = true, default_value_t = Format::Table)]
+    format: Format,
+
+    /// Match pages with all of the --tags (filtered by the wiki), or any of them (every page is fetched & filtered here)
+    #[clap(long, arg_enum, global = true, default_value_t = TagMode::All)]
+    tag_mode: TagMode,
+
+    /// No color, same as --color never
+    #[clap(long, global = true)]
+    no_color: bool,
+
+    /// Print errors & failed pages to stderr as a JSON object rather than prose
+    #[clap(long, global = true)]
+    json_errors: bool,
+
+    /// No progress lines, only results, warnings & errors
+    #[clap(long, short, global = true)]
+    quiet: bool,
+
     /// Verbosity level (can be specified multiple times)
     #[clap(long, short, global = true, parse(from_occurrences))]
     verbose: usize,
 
+    /// Print each GraphQL query & its variables to stderr before it's sent. Implied by -vvv
+    #[clap(long, global = true)]
+    dump_query: bool,
+
     /// Config File
     #[clap(long, global = true, parse(from_os_str))]
     config: Option<std::path::PathBuf>,
@@ -79,17 +476,102 @@ struct GlobalOpts {
     #[clap(long, global = true)]
     api_key: Option<String>,
 
+    /// File holding the GraphQL API Key, e.g. a mounted secret. Used unless --api-key is given
+    #[clap(long, global = true, parse(from_os_str))]
+    api_key_file: Option<std::path::PathBuf>,
+
     /// GraphQL Endpoint
     #[clap(long, global = true)]
     endpoint: Option<String>,
 
-    /// HTTP2 (Default On)
+    /// Your wiki's address, e.g. https://wiki.example.com. The endpoint is worked out from it
+    #[clap(long, global = true, alias = "endpoint-from-url", conflicts_with = "endpoint", parse(try_from_str = endpoint_from_url))]
+    wiki_url: Option<String>,
+
+    /// Use HTTP/2 without negotiating it. Fails against servers (or proxies) which only speak HTTP/1
+    #[clap(long, global = true, conflicts_with = "no-http2-prior-knowledge")]
+    http2_prior_knowledge: bool,
+
+    /// Negotiate HTTP/2, even if the config file presumes it (Default)
     #[clap(long, global = true)]
     no_http2_prior_knowledge: bool,
 
     /// HTTPS (Default On)
     #[clap(long, global = true)]
-    no_force_https: bool
+    no_force_https: bool,
+
+    /// Prefixed to every path & destination. Start a path with // to ignore it
+    #[clap(long, global = true)]
+    base_path: Option<String>,
+
+    /// The wiki's primary locale, which pages are moved into and links are read from [default: en]
+    #[clap(long, global = true)]
+    locale: Option<String>,
+
+    /// A path segment or tag which marks a page as private, ignoring case. Repeat it for several [default: private]
+    #[clap(long = "sensitive-marker", value_name = "MARKER", global = true)]
+    sensitive_markers: Option<Vec<String>>,
+
+    /// Send no more than this many requests per second when listing & moving pages
+    #[clap(long, global = true, parse(try_from_str = parse_rate))]
+    rate: Option<f64>,
+
+    /// How many moves to send at once, or `auto` to ramp up while the wiki keeps up [default: 16]
+    #[clap(long, global = true, parse(try_from_str = parse_concurrency))]
+    concurrency: Option<Concurrency>,
+
+    /// The most moves --concurrency auto will send at once
+    #[clap(long, global = true, default_value_t = 16)]
+    max_concurrency: usize,
+
+    /// Reuse the page list fetched by an earlier `list` or `stats`, if it's no older than --cache-ttl. Moves never do
+    #[clap(long, global = true)]
+    cache: bool,
+
+    /// Seconds a cached page list is reused for
+    #[clap(long, global = true, default_value = "300", parse(try_from_str = parse_seconds))]
+    cache_ttl: Duration,
+
+    /// Fetch the page list even if it's cached, and cache it for next time. Implies --cache
+    #[clap(long, global = true)]
+    refresh: bool,
+
+    /// `minimal` lists pages asking only for their ID, path, locale, title & tags, for servers whose schema differs [default: current]
+    #[clap(long, global = true)]
+    api_version: Option<ApiVersion>,
+
+    /// Advanced: idle connections to keep open to the wiki [default: unlimited]
+    #[clap(long, global = true, hide_short_help = true)]
+    pool_size: Option<usize>,
+
+    /// Advanced: seconds before an idle connection is closed [default: 90]
+    #[clap(long, global = true, hide_short_help = true, parse(try_from_str = parse_seconds))]
+    pool_idle_timeout: Option<Duration>,
+
+    /// Advanced: seconds between pings keeping HTTP/2 connections open
+    #[clap(long, global = true, hide_short_help = true, parse(try_from_str = parse_seconds))]
+    http2_keep_alive: Option<Duration>,
+
+    /// Format each listed page, e.g. "[{title}](/{path})". Placeholders are {id}, {path}, {title}, {tags},
+    /// {editor}, {locale}, {created} & {updated}
+    #[clap(long, global = true, alias = "output-template")]
+    template: Option<String>,
+
+    /// Truncate listed paths to this many characters. Defaults to fitting the terminal
+    #[clap(long, global = true)]
+    max_path_width: Option<usize>,
+
+    /// List pages as plain tab-separated values: full paths, no padding or progress lines
+    #[clap(long, global = true, conflicts_with = "template")]
+    plain: bool,
+
+    /// Don't append /graphql to an endpoint without a path
+    #[clap(long, global = true)]
+    no_auto_graphql: bool,
+
+    /// Rewrite a config file from an older version of wikcli in the current format
+    #[clap(long, global = true)]
+    migrate_config: bool
 }
 
 #[derive(Clone, Copy, Debug, ArgEnum)]
@@ -104,426 +586,3498 @@ impl Color {
         // Set a supports-color override based on the variable passed in.
         match self {
             Color::Always => owo_colors::set_override(true),
-            Color::Auto => {}
+            // <https://no-color.org>: set & not empty means no color
+            Color::Auto => match std::env::var_os("NO_COLOR") {
+                Some(v) if !v.is_empty() => owo_colors::set_override(false),
+                _ => {}
+            },
             Color::Never => owo_colors::set_override(false),
         }
     }
 }
 
 
+/// Layout version of `WikcliConfig`. Bump it, and teach `migrate` the
+/// previous layout, whenever a change would break reading older files.
+const CONFIG_VERSION: u32 = 1;
+
+/// Moves of more pages than this need --force, unless configured otherwise
+const DEFAULT_MAX_PAGES: usize = 100;
+#[derive(Clone, Copy, Debug, PartialEq, ArgEnum)]
+enum Format {
+    Table,
+    Json,
+    /// One JSON object per line, written as each page arrives
+    Ndjson,
+    /// Comma separated, with a header row. Only `list` prints pages this way, others print their table
+    Csv,
+}
+
+#[derive(Clone, Copy, Debug, ArgEnum)]
+enum PageSort {
+    Path,
+    Depth,
+    Title,
+    Id,
+    Created,
+    Updated,
+}
+
+impl PageSort {
+    /// How the wiki orders pages this way, `None` if only wikcli can
+    fn server_order(self) -> Option<PageOrderBy> {
+        match self {
+            PageSort::Path => Some(PageOrderBy::Path),
+            PageSort::Depth => None,
+            PageSort::Title => Some(PageOrderBy::Title),
+            PageSort::Id => Some(PageOrderBy::Id),
+            PageSort::Created => Some(PageOrderBy::Created),
+            PageSort::Updated => Some(PageOrderBy::Updated),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ArgEnum)]
+enum Field {
+    Id,
+    Path,
+    Title,
+    Tags,
+    Editor,
+    Locale,
+    Created,
+    Updated,
+}
+
+impl Field {
+    fn heading(self) -> &'static str {
+        match self {
+            Field::Id => "ID",
+            Field::Path => "Path",
+            Field::Title => "Title",
+            Field::Tags => "Tags",
+            Field::Editor => "Editor",
+            Field::Locale => "Locale",
+            Field::Created => "Created",
+            Field::Updated => "Updated",
+        }
+    }
+
+    /// Its placeholder in a --template
+    fn placeholder(self) -> &'static str {
+        match self {
+            Field::Id => "{id}",
+            Field::Path => "{path}",
+            Field::Title => "{title}",
+            Field::Tags => "{tags}",
+            Field::Editor => "{editor}",
+            Field::Locale => "{locale}",
+            Field::Created => "{created}",
+            Field::Updated => "{updated}",
+        }
+    }
+
+    /// Its key in a page's JSON, see `page_json`
+    fn key(self) -> &'static str {
+        match self {
+            Field::Id => "id",
+            Field::Path => "path",
+            Field::Title => "title",
+            Field::Tags => "tags",
+            Field::Editor => "content_type",
+            Field::Locale => "locale",
+            Field::Created => "created_at",
+            Field::Updated => "updated_at",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ArgEnum)]
+enum Direction {
+    Asc,
+    Desc,
+}
+
+#[derive(Clone, Copy, Debug, ArgEnum)]
+enum TagSort {
+    Name,
+    Count,
+}
+
+#[derive(Clone, Copy, Debug, ArgEnum)]
+enum TagMode {
+    All,
+    Any,
+}
+
+impl TagMode {
+    /// Split `tags` into those for the wiki to filter by, and those to match here.
+    /// Wiki.js only returns pages having all the tags it's given, so `any` asks for every page.
+    fn split(self, tags: Option<Vec<String>>) -> (Option<Vec<String>>, Option<Vec<String>>) {
+        match self {
+            TagMode::All => (tags, None),
+            TagMode::Any => (None, tags),
+        }
+    }
+}
+
+/// Pages under each of their tags, by tag, followed by the pages without any tags
+fn group_by_tags(pages: &[PageListItem]) -> Vec<(&str, Vec<&PageListItem>)> {
+    let mut groups = std::collections::BTreeMap::<&str, Vec<&PageListItem>>::new();
+    let mut untagged = Vec::new();
+    for p in pages {
+        let tags = p.tags.iter().flatten().flatten().unique().collect::<Vec<_>>();
+        if tags.is_empty() {
+            untagged.push(p);
+        }
+        for tag in tags {
+            groups.entry(tag).or_default().push(p);
+        }
+    }
+    groups
+        .into_iter()
+        .chain((!untagged.is_empty()).then_some((UNTAGGED, untagged)))
+        .collect()
+}
+
+/// When no pages have all of several tags, suggest the user may have meant any of them
+fn tag_mode_hint(tags: Option<&Vec<String>>, mode: TagMode) -> Option<String> {
+    match (tags, mode) {
+        (Some(tags), TagMode::All) if tags.len() > 1 => Some(format!(
+            "No pages have ALL of [{}]; did you mean any? Try --tag-mode any.",
+            tags.join(", ")
+        )),
+        _ => None,
+    }
+}
+
+/// What to say, in place of going on, when no `pages` matched `prefix` so there's nothing
+/// to `action`. The --tag-mode `hint` follows, if there is one. `None` if there are pages
+fn nothing_matched(pages: &[PageListItem], prefix: &str, action: Option<&str>, hint: Option<String>) -> Option<String> {
+    if !pages.is_empty() {
+        return None;
+    }
+    let message = match action {
+        Some(action) => format!("No pages matched prefix `{}`, so there's nothing to {}.", prefix, action),
+        None => format!("No pages matched prefix `{}`.", prefix),
+    };
+    Some(match hint {
+        Some(hint) => format!("{}\n{}", message, hint),
+        None => message,
+    })
+}
+
+/// The first line of `content` matching `regex`, or containing `needle` if there's no regex.
+/// `None` unless the content has both, when both are given
+fn matching_line(content: &str, needle: Option<&str>, regex: Option<&Regex>) -> Option<String> {
+    if needle.is_some_and(|needle| !content.contains(needle)) {
+        return None;
+    }
+    content
+        .lines()
+        .find(|l| match (regex, needle) {
+            (Some(regex), _) => regex.is_match(l),
+            (None, Some(needle)) => l.contains(needle),
+            (None, None) => false,
+        })
+        .map(|l| l.trim().to_owned())
+}
+
+/// Whether `page` has at least one of `tags`. Tags are matched case sensitively, like Wiki.js does
+fn has_any_tag(page: &PageListItem, tags: &[String]) -> bool {
+    page.tags.iter().flatten().flatten().any(|t| tags.contains(t))
+}
+
+/// Keep the `pages` which have the `tags`, according to `mode`. Under `all` the wiki
+/// has already done this, but the pages are checked all the same
+fn retain_tagged(pages: &mut Vec<PageListItem>, tags: &[String], mode: TagMode) {
+    match mode {
+        TagMode::All => pages.retain(|p| tags.iter().all(|t| has_any_tag(p, std::slice::from_ref(t)))),
+        TagMode::Any => pages.retain(|p| has_any_tag(p, tags)),
+    }
+}
+
+/// The last page list fetched from a wiki, for --cache
+#[derive(Serialize, Deserialize)]
+struct PageCache {
+    fetched_at: DateTime<Utc>,
+    /// What the wiki filtered the list by, as it's only reused for the same filters
+    filter: Value,
+    pages_returned: usize,
+    pages: Vec<PageListItem>,
+}
+
+/// Where the page list of the wiki at `endpoint` is cached, one file per endpoint
+fn cache_file(endpoint: &str) -> Option<std::path::PathBuf> {
+    let dirs = directories::ProjectDirs::from("rs", "", env!("CARGO_PKG_NAME"))?;
+    let name = endpoint.replace(|c: char| !c.is_ascii_alphanumeric(), "_");
+    Some(dirs.cache_dir().join(format!("pages-{}.json", name)))
+}
+
+/// Like `Wiki::list_pages_where`, but with --cache the whole list is kept on disk
+/// & reused for --cache-ttl. A cache which can't be read or written is ignored
+async fn list_pages_cached(
+    wiki: &Wiki,
+    globals: &GlobalOpts,
+    prefix: &str,
+    filter: ListAllPagesArguments,
+) -> Result<ListPages> {
+    let file = match (globals.cache || globals.refresh, cache_file(wiki.endpoint())) {
+        (true, Some(file)) => file,
+        _ => return Ok(wiki.list_pages_where(prefix, filter).await?),
+    };
+    let key = json!({ "filter": filter, "api_version": format!("{:?}", globals.api_version.unwrap_or_default()) });
+
+    let cached = std::fs::read(&file)
+        .ok()
+        .and_then(|bytes| cynic::serde_json::from_slice::<PageCache>(&bytes).ok())
+        .filter(|cache| !globals.refresh && cache.filter == key)
+        .filter(|cache| Utc::now().signed_duration_since(cache.fetched_at).to_std().is_ok_and(|age| age < globals.cache_ttl));
+
+    let cache = match cached {
+        Some(cache) => {
+            if globals.verbose > 0 {
+                eprintln!("Using the page list cached at {}", cache.fetched_at.to_rfc3339());
+            }
+            cache
+        }
+        None => {
+            let ListPages { pages, pages_returned } = wiki.list_pages_where("", filter).await?;
+            let cache = PageCache { fetched_at: Utc::now(), filter: key, pages_returned, pages };
+            let written = file.parent()
+                .map_or(Ok(()), std::fs::create_dir_all)
+                .and_then(|_| std::fs::write(&file, cynic::serde_json::to_vec(&cache)?));
+            if let Err(e) = written {
+                eprintln!("Could not cache the page list in {}: {}", file.display(), e);
+            }
+            cache
+        }
+    };
+
+    Ok(ListPages {
+        pages: cache.pages.into_iter().filter(|p| p.path.starts_with(prefix)).collect(),
+        pages_returned: cache.pages_returned,
+    })
+}
+
+/// Pages the wiki itself relies on, left out of moves unless configured otherwise.
+/// `home` is the page each locale's wiki opens on
+const DEFAULT_SYSTEM_PAGES: &[&str] = &["home"];
+
+/// The config's system pages, or `DEFAULT_SYSTEM_PAGES`
+fn system_pages(cfg: &WikcliConfig) -> Vec<String> {
+    match &cfg.system_pages {
+        Some(pages) => pages.clone(),
+        None => DEFAULT_SYSTEM_PAGES.iter().map(|p| p.to_string()).collect(),
+    }
+}
+
+/// `--sensitive-marker` if given, otherwise the config's markers, otherwise `DEFAULT_SENSITIVE_MARKERS`
+fn sensitive_markers(cfg: &WikcliConfig, globals: &GlobalOpts) -> Vec<String> {
+    match globals.sensitive_markers.as_ref().or(cfg.sensitive_markers.as_ref()) {
+        Some(markers) => markers.clone(),
+        None => DEFAULT_SENSITIVE_MARKERS.iter().map(|m| m.to_string()).collect(),
+    }
+}
+
+/// Whether `page` is one of the `system` pages, or under one of them
+fn is_system_page(page: &PageListItem, system: &[String]) -> bool {
+    system.iter().any(|s| {
+        let s = s.trim_matches('/');
+        page.path == s || page.path.strip_prefix(s).is_some_and(|rest| rest.starts_with('/'))
+    })
+}
+
+/// `--tags` if given, otherwise the config's default tags. `--tags ""` clears the default
+fn tags_or_default(tags: Option<Vec<String>>, cfg: &WikcliConfig) -> Option<Vec<String>> {
+    match tags {
+        Some(tags) => {
+            let tags = tags.into_iter().filter(|t| !t.is_empty()).collect::<Vec<_>>();
+            (!tags.is_empty()).then_some(tags)
+        }
+        None => cfg.tags.clone(),
+    }
+}
+
+/// List pages beginning with `prefix` which have the `tags`, according to `mode`, in `locale` if given
+async fn list_tagged(wiki: &Wiki, prefix: &str, tags: Option<Vec<String>>, mode: TagMode, locale: Option<String>) -> Result<ListPages> {
+    let (server_tags, _) = mode.split(tags.clone());
+    let filter = ListAllPagesArguments {
+        tags: server_tags,
+        locale,
+        author_id: None,
+        creator_id: None,
+        order_by: None,
+        order_by_direction: None,
+        limit: None,
+        fields: ListFields::ALL,
+    };
+    let mut pages = wiki.list_pages_where(prefix, filter).await?;
+    if let Some(tags) = &tags {
+        retain_tagged(&mut pages.pages, tags, mode);
+    }
+    Ok(pages)
+}
+
 #[derive(Serialize, Deserialize)]
 struct WikcliConfig { 
+    /// Missing in files written before versioning, i.e. version 0
+    version: Option<u32>,
     api_key: Option<String>,
+    /// Read for the API key in preference to `api_key`
+    api_key_file: Option<std::path::PathBuf>,
     endpoint: Option<String>,
     no_http2_prior_knowledge: Option<bool>,
-    no_force_https: Option<bool>
-
+    no_force_https: Option<bool>,
+    max_pages: Option<usize>,
+    base_path: Option<String>,
+    confirm_threshold: Option<usize>,
+    /// Used when --tags isn't given
+    tags: Option<Vec<String>>,
+    /// Pages left out of moves unless --include-system-pages, see `is_system_page`
+    system_pages: Option<Vec<String>>,
+    /// The wiki's primary locale, `DEFAULT_LOCALE` if missing
+    locale: Option<String>,
+    /// Used when --sensitive-marker isn't given, see `sensitive_markers`
+    sensitive_markers: Option<Vec<String>>,
 }
 
 /// Default values for `WikcliConfig`
 impl ::std::default::Default for WikcliConfig {
     fn default() -> Self { 
         Self { 
+            version: Some(CONFIG_VERSION),
             api_key: None, 
+            api_key_file: None,
             endpoint: None, 
             no_http2_prior_knowledge: None, 
-            no_force_https: None
+            no_force_https: None,
+            max_pages: None,
+            base_path: None,
+            confirm_threshold: None,
+            tags: None,
+            system_pages: None,
+            locale: None,
+            sensitive_markers: None,
         } 
     }
 }
 
+impl WikcliConfig {
+    /// Upgrade a config read from an older version in memory.
+    /// Returns the version it was read as, if that was older than `CONFIG_VERSION`.
+    fn migrate(&mut self) -> Option<u32> {
+        let from = self.version.unwrap_or(0);
+        // v0 -> v1: the flat layout is unchanged, only the version is recorded
+        self.version = Some(CONFIG_VERSION.max(from));
+        match from < CONFIG_VERSION {
+            true => Some(from),
+            false => None,
+        }
+    }
+
+    /// Apply a `key=value` from `config --set`. An empty value unsets the key
+    fn set(&mut self, assignment: &str) -> Result<()> {
+        let (key, value) = match assignment.split_once('=') {
+            Some((key, value)) => (key.trim(), value.trim()),
+            None => bail!("`{}` should be KEY=VALUE", assignment),
+        };
+        let value = (!value.is_empty()).then_some(value);
+        let flag = |v: &str| v.parse::<bool>()
+            .with_context(|| format!("{} should be true or false, not `{}`", key, v));
+
+        match key {
+            "api_key" => self.api_key = value.map(str::to_owned),
+            "api_key_file" => self.api_key_file = value.map(std::path::PathBuf::from),
+            "endpoint" => {
+                if let Some(v) = value {
+                    reqwest::Url::parse(v).with_context(|| format!("`{}` isn't a valid endpoint URL", v))?;
+                }
+                self.endpoint = value.map(str::to_owned)
+            }
+            "no_http2_prior_knowledge" => self.no_http2_prior_knowledge = value.map(flag).transpose()?,
+            "no_force_https" => self.no_force_https = value.map(flag).transpose()?,
+            "max_pages" => self.max_pages = value
+                .map(|v| v.parse::<usize>().with_context(|| format!("max_pages should be a number, not `{}`", v)))
+                .transpose()?,
+            "base_path" => self.base_path = value.map(str::to_owned),
+            "confirm_threshold" => self.confirm_threshold = value
+                .map(|v| v.parse::<usize>().with_context(|| format!("confirm_threshold should be a number, not `{}`", v)))
+                .transpose()?,
+            "tags" => self.tags = value.map(|v| v.split(',').map(|t| t.trim().to_owned()).filter(|t| !t.is_empty()).collect()),
+            "system_pages" => self.system_pages = value.map(|v| v.split(',').map(|p| p.trim().to_owned()).filter(|p| !p.is_empty()).collect()),
+            "locale" => self.locale = value.map(str::to_owned),
+            "sensitive_markers" => self.sensitive_markers = value.map(|v| v.split(',').map(|m| m.trim().to_owned()).filter(|m| !m.is_empty()).collect()),
+            _ => bail!(
+                "Unknown config key `{}`. Keys are api_key, api_key_file, endpoint, no_http2_prior_knowledge, no_force_https, max_pages, base_path, confirm_threshold, tags, system_pages, locale & sensitive_markers",
+                key
+            ),
+        }
+        Ok(())
+    }
+}
+
+
+/// Settings after combining flags & the config file. Missing values are `None`
+struct EffectiveConfig {
+    api_key: Option<String>,
+    endpoint: Option<String>,
+    http2: bool,
+    https: bool,
+    dump_query: bool,
+    rate: Option<f64>,
+    locale: String,
+}
+
+/// The API key in `file`, without the trailing newline most tools write
+fn read_api_key_file(file: &std::path::Path) -> Result<String> {
+    let key = std::fs::read_to_string(file)
+        .with_context(|| format!("Could not read the API key file {}", file.display()))?;
+    let key = key.trim_end();
+    if key.is_empty() {
+        bail!("The API key file {} is empty", file.display())
+    }
+    Ok(key.to_owned())
+}
 
-fn wiki_config(cfg: &WikcliConfig, globals: &GlobalOpts) -> Result<lib::WikiConfig> {
-    let api_key = match (&globals.api_key, &cfg.api_key) {
-        (Some(k), _) => k.clone(),
-        (_, Some(k)) => k.clone(), 
-        (None, None) => bail!("You must specify an API key via --api-key or config")
+fn effective_config(cfg: &WikcliConfig, globals: &GlobalOpts) -> Result<EffectiveConfig> {
+    // --api-key, then a key file from the flag or config, then the key in the config
+    let api_key = match (&globals.api_key, globals.api_key_file.as_ref().or(cfg.api_key_file.as_ref())) {
+        (Some(key), _) => Some(key.clone()),
+        (None, Some(file)) => Some(read_api_key_file(file)?),
+        (None, None) => cfg.api_key.clone(),
     };
-    let endpoint = match (&globals.endpoint, &cfg.endpoint) {
-        (Some(k), _) => k.clone(),
-        (_, Some(k)) => k.clone(), 
-        (None, None) => bail!("You must specify an endpoint via --endpoint or config")
+    let endpoint = globals.endpoint.clone()
+        .or_else(|| globals.wiki_url.clone())
+        .or_else(|| cfg.endpoint.clone());
+    let endpoint = match globals.no_auto_graphql {
+        true => endpoint,
+        false => endpoint.map(|endpoint| {
+            let adjusted = with_graphql_path(&endpoint);
+            if adjusted != endpoint && globals.verbose > 0 {
+                eprintln!("Endpoint has no path, using {}", adjusted);
+            }
+            adjusted
+        })
     };
-    // nb: we're inverting from no_http2 to (yes_) http2
-    let http2 = match cfg.no_http2_prior_knowledge {
-        Some(true) => false, // http2 off via config
-        _ => !globals.no_http2_prior_knowledge // http2 off via globals
+    // nb: we're inverting from no_http2 to (yes_) http2. Prior knowledge breaks
+    // HTTP/1-only servers, so it's only used when asked for, and flags beat config
+    let http2 = match (globals.http2_prior_knowledge, globals.no_http2_prior_knowledge) {
+        (true, _) => true, // http2 on via globals
+        (_, true) => false, // http2 off via globals
+        _ => cfg.no_http2_prior_knowledge == Some(false) // http2 on via config
     }; 
     let https = match cfg.no_force_https {
         Some(true) => false, // force https off via config
         _ => !globals.no_force_https // https off via globals
     }; 
-    Ok(lib::WikiConfig { api_key, endpoint, http2, https })
+    let dump_query = globals.dump_query || globals.verbose >= 3;
+    let rate = globals.rate;
+    let locale = globals.locale.clone()
+        .or_else(|| cfg.locale.clone())
+        .unwrap_or_else(|| DEFAULT_LOCALE.to_owned());
+    Ok(EffectiveConfig { api_key, endpoint, http2, https, dump_query, rate, locale })
 }
 
+fn wiki_config(cfg: &WikcliConfig, globals: &GlobalOpts) -> Result<WikiConfig> {
+    let EffectiveConfig { api_key, endpoint, http2, https, dump_query, rate, locale } = effective_config(cfg, globals)?;
+    let api_key = match api_key {
+        Some(k) => k,
+        None => bail!("You must specify an API key via --api-key, --api-key-file or config")
+    };
+    let endpoint = match endpoint {
+        Some(e) => e,
+        None => bail!("You must specify an endpoint via --endpoint or config")
+    };
+    Ok(WikiConfig {
+        api_key,
+        endpoint,
+        http2,
+        https,
+        dump_query,
+        rate,
+        pool_size: globals.pool_size,
+        pool_idle_timeout: globals.pool_idle_timeout,
+        http2_keep_alive: globals.http2_keep_alive,
+        concurrency: match globals.concurrency {
+            Some(Concurrency::Auto { .. }) => Concurrency::Auto { max: globals.max_concurrency.max(1) },
+            Some(fixed) => fixed,
+            None => Concurrency::default(),
+        },
+        api_version: globals.api_version.unwrap_or_default(),
+        locale,
+        sensitive_markers: sensitive_markers(cfg, globals),
+    })
+}
 
-struct Styles {
-    scaffold: Style,
-    message: Style,
-    user: Style,
-    output: Style,
+/// Create the client, and warn if the wiki is a version of Wiki.js this build
+/// may not understand. The check never stops the command. With -v, say which wiki it is
+async fn connect(cfg: &WikcliConfig, globals: &GlobalOpts) -> Result<Wiki> {
+    let wiki = Wiki::new(wiki_config(cfg, globals)?);
+    check_and_announce(&wiki, globals).await;
+    Ok(wiki)
 }
 
-#[tokio::main]
-async fn main() -> Result<()> {
-    // Make panic message more useful
-    human_panic::setup_panic!();
+/// `check_version` & `announce` at once, as neither waits on the other
+async fn check_and_announce(wiki: &Wiki, globals: &GlobalOpts) {
+    tokio::join!(check_version(wiki, globals), announce(wiki, globals));
+}
 
-    let app = App::parse();
+/// Warn if the wiki's version isn't one this build targets
+async fn check_version(wiki: &Wiki, globals: &GlobalOpts) {
+    match wiki.get_server_version().await {
+        Ok(version) if !is_supported_version(&version) => eprintln!(
+            "This build targets Wiki.js {}.x; your server reports {} — some commands may fail. Try --api-version minimal if listing pages does.",
+            SUPPORTED_MAJOR_VERSION,
+            version
+        ),
+        Ok(_) => {}
+        // not every API key may see system info, so only mention it when asked
+        Err(e) if globals.verbose > 0 => eprintln!("Could not check the wiki's version: {}", e),
+        Err(_) => {}
+    }
+}
 
-    let cfg: WikcliConfig = match app.global_opts.config {
-        Some(ref p) => {confy::load_path(p)?}
-        None => {confy::load(env!("CARGO_PKG_NAME"))?}
-    };
+/// With -v, print which wiki a command is running against, so logs of several wikis
+/// show which one each command ran against. Commands which list pages run this
+/// alongside the listing, rather than waiting on the title first
+async fn announce(wiki: &Wiki, globals: &GlobalOpts) {
+    if globals.verbose == 0 {
+        return;
+    }
+    let host = reqwest::Url::parse(wiki.endpoint())
+        .ok()
+        .and_then(|url| url.host_str().map(str::to_owned))
+        .unwrap_or_else(|| wiki.endpoint().to_owned());
+    match wiki.get_wiki_title().await {
+        Ok(title) => eprintln!("Connected to: {} ({})", title, host),
+        Err(e) => eprintln!("Connected to: {} (could not fetch the wiki's title: {})", host, e),
+    }
+}
 
-    // Windows 10 Terminals can do ANSI colors with your help!
-    match enable_ansi_support::enable_ansi_support() {
-        Ok(_) => app.global_opts.color.init(),
-        Err(_) => {}
+/// The GraphQL endpoint of the wiki at `url`, which is `/graphql` under it,
+/// e.g. https://wiki.example.com/ -> https://wiki.example.com/graphql
+fn endpoint_from_url(url: &str) -> Result<String> {
+    let mut url = reqwest::Url::parse(url.trim())
+        .with_context(|| format!("`{}` isn't a valid URL, e.g. https://wiki.example.com", url))?;
+    if !url.path().ends_with("/graphql") {
+        let path = format!("{}/graphql", url.path().trim_end_matches('/'));
+        url.set_path(&path);
     }
+    url.set_query(None);
+    url.set_fragment(None);
+    Ok(url.to_string())
+}
 
-    let term = Term::stdout();
+/// Wiki.js serves GraphQL from `/graphql`, so append it to a bare host.
+/// Endpoints with any other path, e.g. `/custom/graphql`, are left alone.
+fn with_graphql_path(endpoint: &str) -> String {
+    match reqwest::Url::parse(endpoint) {
+        Ok(mut url) if url.path() == "/" => {
+            url.set_path("/graphql");
+            url.to_string()
+        }
+        _ => endpoint.to_owned(),
+    }
+}
 
-    let styles = Styles {
-        scaffold: Style::new().bright_white().on_black(),
-        message: Style::new().blue().on_black(),
-        user: Style::new()
-            .bg::<xterm::ElectricIndigo>()
-            .fg::<xterm::Copperfield>()
-            .underline(),
-        output: Style::new().fg::<xterm::ElectricIndigo>().on_bright_white(),
-    };
+/// Column headings for listed pages
+const HEADER: &str = "ID\tPath\tTitle\tTags";
 
+/// Room left for the ID, title & tags when fitting the path column to the terminal
+const OTHER_COLUMNS_WIDTH: usize = 40;
 
-    match app.command {
-        Command::Config { output, interactive } => {
-            let api_key = match &app.global_opts.api_key {
-                Some(k) => Some(k.clone()),
-                None => match interactive {
-                    false => None,
-                    true => {Some(dialoguer::Password::new()
-                        .with_prompt("Enter your API Key: ")
-                        .interact()?)}
-                }
+/// Narrowest the path column is squeezed to when fitting it to the terminal
+const MIN_PATH_WIDTH: usize = 20;
+
+/// Terminal width less the other columns, or `None` when stdout isn't a terminal
+fn default_path_width() -> Option<usize> {
+    let term = Term::stdout();
+    match term.is_term() {
+        true => Some((term.size().1 as usize).saturating_sub(OTHER_COLUMNS_WIDTH).max(MIN_PATH_WIDTH)),
+        false => None,
+    }
+}
+
+/// Shown in place of a missing title
+const UNTITLED: &str = "[Untitled]";
+
+/// Heading of the pages without tags in --group-by-tag
+const UNTAGGED: &str = "(untagged)";
+
+/// Used when no `--template` is given, matching `HEADER`
+const DEFAULT_TEMPLATE: &str = "{id}\t{path}\t{title} ({tags})";
+/// Template used by --plain, with tags in a column of their own
+const PLAIN_TEMPLATE: &str = "{id}\t{path}\t{title}\t{tags}";
+
+/// Format a line per page from `template`, leaving unknown placeholders alone.
+/// 
+/// Without a template, `DEFAULT_TEMPLATE` is used and paths have the prefix
+/// `trim` removed & are padded into a column, no wider than `max_width`.
+fn format_pages<'a>(
+    pages: impl IntoIterator<Item = &'a PageListItem> + Clone,
+    template: Option<&str>,
+    trim: &str,
+    max_width: Option<usize>,
+) -> Vec<String> {
+    // paths may not be ASCII, so strip whole prefixes & measure what's displayed
+    let trimmed = |p: &'a PageListItem| p.path.strip_prefix(trim).unwrap_or(&p.path);
+    let max_path = pages.clone().into_iter().map(|p| console::measure_text_width(trimmed(p))).max().unwrap_or(50);
+    let max_path = max_path.min(max_width.unwrap_or(usize::MAX));
+
+    pages
+        .into_iter()
+        .map(|p| {
+            let path = match template {
+                Some(_) => p.path.clone(),
+                None => console::pad_str(trimmed(p), max_path, console::Alignment::Left, Some("…"))
+                    .into_owned(),
+            };
+            let mut line = String::new();
+            let mut rest = template.unwrap_or(DEFAULT_TEMPLATE);
+            while let Some(start) = rest.find('{') {
+                line.push_str(&rest[..start]);
+                rest = &rest[start..];
+                let end = rest.find('}').map_or(rest.len(), |e| e + 1);
+                match &rest[..end] {
+                    "{id}" => line.push_str(&p.id.to_string()),
+                    "{path}" => line.push_str(&path),
+                    "{title}" => line.push_str(p.title.as_deref().unwrap_or(UNTITLED)),
+                    "{tags}" => line.push_str(&match &p.tags {
+                        Some(ts) => ts.iter().flatten().join(", "),
+                        None => String::new(),
+                    }),
+                    "{editor}" => line.push_str(&p.content_type),
+                    "{locale}" => line.push_str(&p.locale),
+                    "{created}" => line.push_str(&p.created_at.to_rfc3339()),
+                    "{updated}" => line.push_str(&p.updated_at.to_rfc3339()),
+                    other => line.push_str(other),
+                }
+                rest = &rest[end..];
+            }
+            line.push_str(rest);
+            line
+        })
+        .collect()
+}
+
+/// `from -> to`, with the part of each which differs highlighted
+fn path_diff(from: &str, to: &str) -> String {
+    let same_start: usize = from.chars().zip(to.chars()).take_while(|(a, b)| a == b).map(|(c, _)| c.len_utf8()).sum();
+    let (from_rest, to_rest) = (&from[same_start..], &to[same_start..]);
+    let same_end: usize = from_rest.chars().rev().zip(to_rest.chars().rev()).take_while(|(a, b)| a == b).map(|(c, _)| c.len_utf8()).sum();
+
+    let highlight = |path: &str, style: Style| {
+        let changed = &path[same_start..path.len() - same_end];
+        format!(
+            "{}{}{}",
+            &path[..same_start],
+            changed.if_supports_color(Stream::Stdout, |text| text.style(style)),
+            &path[path.len() - same_end..]
+        )
+    };
+    format!(
+        "{} -> {}",
+        highlight(from, Style::new().red().bold()),
+        highlight(to, Style::new().green().bold())
+    )
+}
+
+/// Whether `page` has no title, or a blank one
+fn is_untitled(page: &PageListItem) -> bool {
+    page.title.as_deref().is_none_or(|t| t.trim().is_empty())
+}
+
+/// Extra columns for a listed page with -v
+fn verbose_columns(p: &PageListItem) -> String {
+    format!(
+        "\t{}\t{}\t{}\t{}",
+        p.locale,
+        p.content_type,
+        p.created_at.to_rfc3339(),
+        p.updated_at.to_rfc3339()
+    )
+}
+
+/// Prefix `path` with `base_path`, unless it starts with `//`.
+/// 
+/// Paths & destinations are both resolved before any prefix is replaced,
+/// so `move teams -d people` with a base path of `handbook` replaces
+/// `handbook/teams` with `handbook/people`.
+fn resolve_path(base_path: Option<&str>, path: &str) -> String {
+    match (path.strip_prefix("//"), base_path) {
+        (Some(absolute), _) => absolute.to_owned(),
+        (None, Some(base)) => format!("{}/{}", base.trim_end_matches('/'), path.trim_start_matches('/')),
+        (None, None) => path.to_owned(),
+    }
+}
+
+/// How long requests took to be answered
+struct LatencySummary {
+    min: Duration,
+    median: Duration,
+    p95: Duration,
+    max: Duration,
+}
+
+impl LatencySummary {
+    /// `latencies` mustn't be empty
+    fn of(latencies: &[Duration]) -> LatencySummary {
+        let sorted = latencies.iter().copied().sorted().collect::<Vec<_>>();
+        // nearest rank
+        let percentile = |p: usize| sorted[(sorted.len() * p).div_ceil(100).max(1) - 1];
+        LatencySummary {
+            min: sorted[0],
+            median: sorted[sorted.len() / 2],
+            p95: percentile(95),
+            max: sorted[sorted.len() - 1],
+        }
+    }
+
+    fn json(&self) -> Value {
+        json!({
+            "min": self.min.as_secs_f64(),
+            "median": self.median.as_secs_f64(),
+            "p95": self.p95.as_secs_f64(),
+            "max": self.max.as_secs_f64(),
+        })
+    }
+}
+
+impl std::fmt::Display for LatencySummary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "min {:.3}s, median {:.3}s, p95 {:.3}s, max {:.3}s",
+            self.min.as_secs_f64(),
+            self.median.as_secs_f64(),
+            self.p95.as_secs_f64(),
+            self.max.as_secs_f64()
+        )
+    }
+}
+
+/// Make sure everything written so far is shown before blocking on a prompt
+fn flush_output(term: &Term) -> Result<()> {
+    term.flush()?;
+    std::io::Write::flush(&mut std::io::stdout())?;
+    Ok(())
+}
+
+/// Ask `prompt` on stderr, once everything written to `term` before it has been shown.
+/// When stdout is piped, what's being confirmed went down the pipe, so say where it went
+fn confirm(term: &Term, prompt: &str) -> Result<bool> {
+    flush_output(term)?;
+    let stderr = Term::stderr();
+    if !term.is_term() && stderr.is_term() {
+        stderr.write_line("The details of what you're confirming were written to stdout, which isn't this terminal.")?;
+    }
+    Ok(Confirm::new().with_prompt(prompt).interact_on(&stderr)?)
+}
+
+/// How `PageChecks` words its messages & questions for a command
+struct PageAction {
+    /// e.g. "move", as in "nothing to move"
+    verb: &'static str,
+    /// e.g. "moved", as in "will be moved"
+    done: &'static str,
+    /// Asked before going ahead with private pages
+    private_prompt: &'static str,
+    /// The user didn't go ahead with private pages
+    private_aborted: &'static str,
+}
+
+const MOVING: PageAction = PageAction {
+    verb: "move",
+    done: "moved",
+    private_prompt: "Moving private pages may change who can access them.\nAre you really sure you want to move private pages?",
+    private_aborted: "User was not really sure they want to move private pages.",
+};
+
+const DELETING: PageAction = PageAction {
+    verb: "delete",
+    done: "deleted",
+    private_prompt: "A deleted page can't be moved back.\nAre you really sure you want to delete private pages?",
+    private_aborted: "User was not really sure they want to delete private pages.",
+};
+
+/// The steps Move & Delete share between listing pages and changing them
+struct PageChecks<'a> {
+    action: &'a PageAction,
+    term: &'a Term,
+    reporter: &'a dyn Reporter,
+    cfg: &'a WikcliConfig,
+    template: Option<&'a str>,
+    max_path_width: Option<usize>,
+}
+
+impl PageChecks<'_> {
+    /// Leave system pages out of the `listed` pages unless `include_system_pages`, then
+    /// say so and return `None` if none are left. More than `max_pages` are refused unless `force`
+    fn narrow(
+        &self,
+        listed: ListPages,
+        prefix: &str,
+        hint: Option<String>,
+        include_system_pages: bool,
+        max_pages: Option<usize>,
+        force: bool,
+    ) -> Result<Option<Vec<PageListItem>>> {
+        let ListPages { mut pages, pages_returned } = listed;
+        if !include_system_pages {
+            let system = system_pages(self.cfg);
+            let (kept, left): (Vec<_>, Vec<_>) = pages.into_iter().partition(|p| !is_system_page(p, &system));
+            pages = kept;
+            if !left.is_empty() {
+                self.reporter.note(&format!(
+                    "Leaving {} system pages alone, add --include-system-pages to {} them too: {}",
+                    left.len(),
+                    self.action.verb,
+                    left.iter().map(|p| &p.path).join(", ")
+                ))?;
+            }
+        }
+
+        self.reporter.pages_found("Formatting", pages.len(), "matching pages", Some(pages_returned))?;
+
+        if let Some(message) = nothing_matched(&pages, prefix, Some(self.action.verb), hint) {
+            self.term.write_line(&message)?;
+            return Ok(None);
+        }
+
+        let max_pages = max_pages.or(self.cfg.max_pages).unwrap_or(DEFAULT_MAX_PAGES);
+        if pages.len() > max_pages && !force {
+            bail!(
+                "{} pages match, which is more than the limit of {}. Use --force, or raise --max-pages, if you really mean to {} them all.",
+                pages.len(),
+                max_pages,
+                self.action.verb
+            )
+        }
+        Ok(Some(pages))
+    }
+
+    /// The `pages` which look private, or none at all with `no_safety_check`, which is warned
+    /// about. `deep_scan` also checks the content of those not flagged by their path & tags.
+    /// With `fail_on_private`, finding any stops here with `PrivatePagesFound`
+    async fn private<'p>(
+        &self,
+        wiki: &Wiki,
+        pages: &'p [PageListItem],
+        no_safety_check: bool,
+        deep_scan: bool,
+        fail_on_private: bool,
+    ) -> Result<Vec<&'p PageListItem>> {
+        if no_safety_check {
+            Term::stderr().write_line(&format!(
+                "{} {}",
+                Emoji("⚠️ ", ""),
+                format!("WARNING: --no-safety-check given, private pages will be {} without asking!", self.action.done)
+                    .if_supports_color(Stream::Stderr, |text| text.style(Style::new().red().bold()))
+            ))?;
+            return Ok(Vec::new());
+        }
+        let mut private_pages = wiki.safety_check_private(pages.iter());
+
+        if deep_scan {
+            self.reporter.note(&format!(
+                "Scanning page content for {}, this makes a request per page.",
+                PRIVATE_MARKER
+            ))?;
+            let unflagged = pages
+                .iter()
+                .filter(|p| !private_pages.iter().any(|pp| pp.id == p.id))
+                .collect::<Vec<_>>();
+            private_pages.extend(wiki.safety_check_private_deep(unflagged.into_iter()).await?);
+        }
+
+        if fail_on_private && !private_pages.is_empty() {
+            bail!(PrivatePagesFound(private_pages.iter().map(|p| p.path.clone()).collect()))
+        }
+        Ok(private_pages)
+    }
+
+    /// Ask whether to go ahead unless `yes`, then list any `private_pages`, trimmed of
+    /// `trim`, and ask again about those whatever `yes` says
+    fn confirm(&self, yes: bool, private_pages: &[&PageListItem], trim: &str) -> Result<()> {
+        let proceed = yes || confirm(self.term, "Are you sure you want to do this?")?;
+
+        if !proceed {
+            bail!(Aborted("User was not sure they want to do this."))
+        }
+
+        if !private_pages.is_empty() {
+            self.term.write_line(&format!("The following pages you intend to {} are marked as private:", self.action.verb))?;
+            let lines = format_pages(private_pages.iter().copied(), self.template, trim, self.max_path_width).join("\n");
+            self.term.write_line(&lines)?;
+
+            let proceed = confirm(self.term, self.action.private_prompt)?;
+
+            if !proceed {
+                bail!(Aborted(self.action.private_aborted))
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Process exit codes, so scripts can tell outcomes apart
+#[derive(Clone, Copy, Debug)]
+enum Exit {
+    Success = 0,
+    Error = 1,
+    PartialFailure = 2,
+    AllFailed = 3,
+    Aborted = 4,
+    PrivatePages = 5,
+}
+
+impl Exit {
+    /// Exit code for a bulk operation which returned `failures`
+    fn from_failures<T>(success_count: usize, failures: &Option<Vec<T>>) -> Exit {
+        match (failures, success_count) {
+            (None, _) => Exit::Success,
+            (Some(_), 0) => Exit::AllFailed,
+            (Some(_), _) => Exit::PartialFailure,
+        }
+    }
+}
+
+/// The user declined to go ahead with an operation
+#[derive(Debug)]
+struct Aborted(&'static str);
+
+impl std::fmt::Display for Aborted {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.0)
+    }
+}
+
+impl std::error::Error for Aborted {}
+
+/// Pages which look private were matched, and --fail-on-private was given
+#[derive(Debug)]
+struct PrivatePagesFound(Vec<String>);
+
+impl std::fmt::Display for PrivatePagesFound {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Stopping, as --fail-on-private was given and these {} pages look private:\n{}",
+            self.0.len(),
+            self.0.join("\n")
+        )
+    }
+}
+
+impl std::error::Error for PrivatePagesFound {}
+
+fn parse_rate(s: &str) -> Result<f64> {
+    match s.parse::<f64>() {
+        Ok(rate) if rate.is_finite() && rate > 0.0 => Ok(rate),
+        _ => bail!("`{}` is not a positive number of requests per second", s),
+    }
+}
+
+fn parse_concurrency(s: &str) -> Result<Concurrency> {
+    match s {
+        // the ceiling comes from --max-concurrency
+        "auto" => Ok(Concurrency::Auto { max: 0 }),
+        _ => match s.parse::<usize>() {
+            Ok(n) if n > 0 => Ok(Concurrency::Fixed(n)),
+            _ => bail!("`{}` is not a positive number of moves, or auto", s),
+        },
+    }
+}
+
+fn parse_seconds(s: &str) -> Result<Duration> {
+    let seconds = s.parse::<f64>()
+        .with_context(|| format!("`{}` is not a number of seconds", s))?;
+    Duration::try_from_secs_f64(seconds)
+        .with_context(|| format!("`{}` is not a valid number of seconds", s))
+}
+
+/// A `--timeout-per-page`, which would time out nearly every page if it were under a second
+fn parse_timeout(s: &str) -> Result<Duration> {
+    let timeout = parse_seconds(s)?;
+    if timeout < Duration::from_secs(1) {
+        bail!("`{}` is too short, it must be at least 1 second", s)
+    }
+    Ok(timeout)
+}
+
+fn parse_timestamp(s: &str) -> Result<DateTime<Utc>> {
+    let timestamp = DateTime::parse_from_rfc3339(s)
+        .with_context(|| format!("`{}` is not an RFC 3339 timestamp, e.g. 2022-01-31T00:00:00Z", s))?;
+    Ok(timestamp.with_timezone(&Utc))
+}
+
+
+/// Line number, prefix & destination of a move in a batch file
+type BatchMove = (usize, String, String);
+
+/// Split batch file lines into moves.
+/// Blank lines & `#` comments are ignored. Malformed lines are returned as
+/// `(line number, line)`.
+fn parse_batch(contents: &str) -> (Vec<BatchMove>, Vec<(usize, String)>) {
+    contents
+        .lines()
+        .enumerate()
+        .map(|(i, line)| (i + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+        .partition_map(|(n, line)| {
+            let separator = match line.contains('\t') {
+                true => '\t',
+                false => ',',
+            };
+            match line.split(separator).map(str::trim).collect::<Vec<_>>()[..] {
+                [prefix, destination] if !prefix.is_empty() && !destination.is_empty() => {
+                    itertools::Either::Left((n, prefix.to_owned(), destination.to_owned()))
+                }
+                _ => itertools::Either::Right((n, line.to_owned())),
+            }
+        })
+}
+
+/// The pages named on stdin, one ID or path per line, found in a single listing
+/// of the wiki. Paths are resolved against the base path like any other.
+async fn pages_from_stdin(wiki: &Wiki, base_path: Option<&str>) -> Result<ListPages> {
+    let input = std::io::read_to_string(std::io::stdin())
+        .context("Could not read pages from stdin")?;
+    let wanted = input
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty())
+        .unique()
+        .collect::<Vec<_>>();
+
+    let ListPages { pages, pages_returned } = wiki.list_pages("", None).await?;
+
+    let mut found = Vec::new();
+    let mut missing = Vec::new();
+    for line in wanted {
+        let page = match line.parse::<i32>() {
+            Ok(id) => pages.iter().position(|p| p.id == id),
+            Err(_) => {
+                let path = resolve_path(base_path, line);
+                pages.iter().position(|p| p.path == path)
+            }
+        };
+        match page {
+            Some(i) => found.push(i),
+            None => missing.push(line),
+        }
+    }
+
+    if !missing.is_empty() {
+        bail!("No page was found for these lines of stdin:\n{}", missing.join("\n"))
+    }
+
+    let pages = pages
+        .into_iter()
+        .enumerate()
+        .filter(|(i, _)| found.contains(i))
+        .map(|(_, p)| p)
+        .collect();
+    Ok(ListPages { pages, pages_returned })
+}
+
+/// Describe each move which would land on the same path as another move,
+/// or on a page which already exists at the destination.
+async fn find_collisions(
+    wiki: &Wiki,
+    pages: &[PageListItem],
+    to: &Destination,
+) -> Result<Vec<String>> {
+    let destinations = pages
+        .iter()
+        .map(|p| (p, to.of(p)))
+        .collect::<Vec<_>>();
+
+    // a transformed path could land anywhere
+    let search = match to {
+        Destination::Prefix { destination, .. } => destination.as_str(),
+        Destination::Transform(_) | Destination::Exact(_) => "",
+    };
+    let ListPages { pages: existing, .. } = wiki.list_pages(search, None).await?;
+
+    let duplicates = destinations
+        .iter()
+        .into_group_map_by(|(_, d)| d)
+        .into_iter()
+        .filter(|(_, group)| group.len() > 1)
+        .flat_map(|(d, group)| {
+            group.into_iter().map(move |(p, _)| format!("{} -> {} (shared with another move)", p.path, d))
+        });
+
+    let existing = destinations
+        .iter()
+        .filter_map(|(p, d)| {
+            existing
+                .iter()
+                .find(|e| &e.path == d && e.id != p.id)
+                .map(|e| format!("{} -> {} (page {} already exists)", p.path, d, e.id))
+        });
+
+    Ok(duplicates.chain(existing).sorted().collect())
+}
+
+/// A page's move, as printed by `move --dry-run --format json` & read back by --plan-file
+#[derive(Serialize, Deserialize)]
+struct PlannedMove {
+    id: i32,
+    old_path: String,
+    new_path: String,
+    /// The page's locale when planned, which decides whether the move changes it
+    locale: String,
+    /// Kept so the page can be checked for privacy again when the plan is carried out
+    tags: Vec<String>,
+    /// Informational: pages are checked again when the plan is carried out
+    would_flag_private: bool,
+}
+
+impl PlannedMove {
+    /// The page to move. Moves & the privacy check only need its ID, path, locale &
+    /// tags, so the rest is left empty
+    fn page(&self) -> PageListItem {
+        PageListItem {
+            id: self.id,
+            path: self.old_path.clone(),
+            locale: self.locale.clone(),
+            tags: Some(self.tags.iter().cloned().map(Some).collect()),
+            title: None,
+            content_type: String::new(),
+            created_at: DateTime::default(),
+            updated_at: DateTime::default(),
+        }
+    }
+}
+
+fn read_plan(file: &std::path::Path) -> Result<Vec<PlannedMove>> {
+    let contents = std::fs::read_to_string(file)
+        .with_context(|| format!("Could not read plan file {}", file.display()))?;
+    cynic::serde_json::from_str(&contents)
+        .with_context(|| format!("{} isn't a plan printed by `move --dry-run --format json`", file.display()))
+}
+
+/// Each page's old & new path, as the move would compute it, with the arrows lined up
+fn format_dry_run(pages: &[PageListItem], to: &Destination) -> String {
+    let width = pages.iter().map(|p| p.path.chars().count()).max().unwrap_or(0);
+    pages
+        .iter()
+        .map(|p| format!("{:width$} -> {}", p.path, to.of(p), width = width))
+        .join("\n")
+}
+
+/// Write a JSON array with the old & new path, locale and outcome of each page's move.
+/// Pages which were never sent are included as `not_attempted`.
+fn write_move_report(
+    file: &std::path::Path,
+    pages: &[PageListItem],
+    to: &Destination,
+    locale: &str,
+    moves: &MoveSuccess,
+) -> Result<()> {
+    std::fs::write(file, cynic::serde_json::to_string_pretty(&move_report(pages, to, locale, moves))?)
+        .with_context(|| format!("Could not write report file {}", file.display()))
+}
+
+/// Print each page's old & new path, locale and outcome as a table, in the order they were listed
+fn print_move_audit(term: &Term, pages: &[PageListItem], to: &Destination, locale: &str, moves: &MoveSuccess) -> Result<()> {
+    term.write_line("ID\tFrom\tTo\tLocale\tOutcome")?;
+    for row in move_report(pages, to, locale, moves) {
+        term.write_line(&format!(
+            "{}\t{}\t{}\t{} -> {}\t{}",
+            row["id"],
+            row["from"].as_str().unwrap_or_default(),
+            row["to"].as_str().unwrap_or_default(),
+            row["from_locale"].as_str().unwrap_or_default(),
+            row["to_locale"].as_str().unwrap_or_default(),
+            row["outcome"].as_str().unwrap_or_default()
+        ))?;
+    }
+    Ok(())
+}
+
+/// Every page's old & new path and locale, and what happened to it, including pages never sent
+fn move_report(pages: &[PageListItem], to: &Destination, locale: &str, moves: &MoveSuccess) -> Vec<Value> {
+    pages
+        .iter()
+        .map(|p| {
+            let mut row = move_outcome(p, to, moves);
+            row["from_locale"] = json!(p.locale);
+            row["to_locale"] = json!(locale);
+            row
+        })
+        .collect()
+}
+
+/// What happened to page `p` in `moves`
+fn move_outcome(p: &PageListItem, to: &Destination, moves: &MoveSuccess) -> Value {
+    match moves.outcomes.iter().find(|o| o.id == p.id) {
+        Some(o) => match &o.result {
+            Ok(rs) => json!({
+                "id": o.id,
+                "from": o.from,
+                "to": o.to,
+                "outcome": if rs.succeeded { "moved" } else { "failed" },
+                "error_code": rs.error_code,
+                "slug": rs.slug,
+                "message": rs.message,
+                "steps": o.steps,
+            }),
+            Err(e) => json!({
+                "id": o.id,
+                "from": o.from,
+                "to": o.to,
+                "outcome": "error",
+                "message": e,
+            }),
+        },
+        None => json!({
+            "id": p.id,
+            "from": p.path,
+            "to": to.of(p),
+            "outcome": "not_attempted",
+        }),
+    }
+}
+
+/// A listed page for --format ndjson, with the line matching --content-contains if any
+fn page_json(p: &PageListItem, snippet: Option<&String>) -> Value {
+    let mut page = json!({
+        "id": p.id,
+        "path": p.path,
+        "locale": p.locale,
+        "title": p.title,
+        "tags": p.tags.iter().flatten().flatten().collect::<Vec<_>>(),
+        "content_type": p.content_type,
+        "created_at": p.created_at.to_rfc3339(),
+        "updated_at": p.updated_at.to_rfc3339(),
+    });
+    if let Some(snippet) = snippet {
+        page["match"] = json!(snippet);
+    }
+    page
+}
+
+/// Only the `fields` of a page from `page_json`, if any were chosen. A --content-contains match is kept
+fn select_fields(mut page: Value, fields: Option<&[Field]>) -> Value {
+    if let (Some(fields), Value::Object(map)) = (fields, &mut page) {
+        map.retain(|key, _| key == "match" || fields.iter().any(|f| f.key() == key));
+    }
+    page
+}
+
+/// Columns of `list --format csv` when no --fields are chosen, in `page_json`'s keys
+const CSV_FIELDS: [Field; 8] = [
+    Field::Id,
+    Field::Path,
+    Field::Locale,
+    Field::Title,
+    Field::Tags,
+    Field::Editor,
+    Field::Created,
+    Field::Updated,
+];
+
+/// Quote a CSV value if it holds a comma, quote or line break, doubling any quotes
+fn csv_escape(value: &str) -> String {
+    match value.contains([',', '"', '\n', '\r']) {
+        true => format!("\"{}\"", value.replace('"', "\"\"")),
+        false => value.to_owned(),
+    }
+}
+
+/// The header row of `list --format csv`, with a `match` column for --content-contains
+fn csv_header(fields: &[Field], with_match: bool) -> String {
+    fields
+        .iter()
+        .map(|f| f.key())
+        .chain(with_match.then_some("match"))
+        .join(",")
+}
+
+/// A page's `fields` from `page_json` as a CSV row. Nulls are left empty & tags joined with commas
+fn page_csv(p: &PageListItem, fields: &[Field], snippet: Option<&String>) -> String {
+    let page = page_json(p, snippet);
+    let cell = |value: &Value| match value {
+        Value::Null => String::new(),
+        Value::String(s) => csv_escape(s),
+        Value::Array(items) => csv_escape(&items.iter().filter_map(Value::as_str).join(",")),
+        other => csv_escape(&other.to_string()),
+    };
+    fields
+        .iter()
+        .map(|f| cell(&page[f.key()]))
+        .chain(snippet.map(|_| cell(&page["match"])))
+        .join(",")
+}
+
+/// A page which failed, for --json-errors. Batch failures carry their line number
+fn failure_json(line: Option<usize>, rs: &ResponseStatus) -> Value {
+    let mut failure = json!({
+        "error_code": rs.error_code,
+        "slug": rs.slug,
+        "message": rs.message,
+        "explanation": explain_failure(rs),
+        "category": FailureCategory::of(rs).name(),
+    });
+    if let Some(n) = line {
+        failure["line"] = json!(n);
+    }
+    failure
+}
+
+/// Print an error, and any failed pages behind it, as one JSON object on stderr
+fn print_json_error(error: &str, failures: Vec<Value>) {
+    eprintln!("{}", json!({ "error": error, "failures": failures }));
+}
+
+/// Plain explanations of the page errors Wiki.js (2.x) returns, and those wikcli records itself, by slug,
+/// each of which names its error code
+const PAGE_ERRORS: &[(&str, FailureCategory, &str)] = &[
+    ("PageGenericError", FailureCategory::ServerError, "The wiki hit an unexpected error with this page"),
+    ("PageDuplicateCreate", FailureCategory::Collision, "A page already exists at the destination path"),
+    ("PageNotFound", FailureCategory::NotFound, "The page doesn't exist, it may have been moved or deleted already"),
+    ("PageEmptyContent", FailureCategory::Validation, "The page has no content, which the wiki doesn't allow"),
+    ("PageIllegalPath", FailureCategory::Validation, "The destination path has characters the wiki doesn't allow"),
+    ("PagePathCollision", FailureCategory::Collision, "A page already exists at the destination path"),
+    ("PageCreateForbidden", FailureCategory::Permission, "The API key isn't allowed to create pages at the destination"),
+    ("PageUpdateForbidden", FailureCategory::Permission, "The API key isn't allowed to update this page"),
+    ("PageMoveForbidden", FailureCategory::Permission, "The API key isn't allowed to move this page to the destination"),
+    ("PageDeleteForbidden", FailureCategory::Permission, "The API key isn't allowed to delete this page"),
+    ("PageRestoreForbidden", FailureCategory::Permission, "The API key isn't allowed to restore this page"),
+    ("RequestFailed", FailureCategory::ServerError, "The request for this page failed before the wiki answered"),
+    ("CopyUnverified", FailureCategory::ServerError, "The copy wasn't at its destination afterwards, or its content differed"),
+];
+
+fn explain_failure(rs: &ResponseStatus) -> Option<&'static str> {
+    PAGE_ERRORS.iter().find(|(slug, _, _)| *slug == rs.slug).map(|(_, _, explanation)| *explanation)
+}
+
+/// Broad cause of a failed page, so a long list of failures can be summed up
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+enum FailureCategory {
+    Permission,
+    Collision,
+    NotFound,
+    Validation,
+    /// Unexpected errors, timeouts & anything the wiki doesn't name
+    ServerError,
+}
+
+impl FailureCategory {
+    fn of(rs: &ResponseStatus) -> FailureCategory {
+        PAGE_ERRORS
+            .iter()
+            .find(|(slug, _, _)| *slug == rs.slug)
+            .map_or(FailureCategory::ServerError, |(_, category, _)| *category)
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            FailureCategory::Permission => "permission",
+            FailureCategory::Collision => "collision",
+            FailureCategory::NotFound => "not found",
+            FailureCategory::Validation => "validation",
+            FailureCategory::ServerError => "server error",
+        }
+    }
+}
+
+/// Move the pages which did move back to where they were, after a move which partly failed.
+/// Failures of the rollback are reported apart from the move's, with the pages left at their new path
+async fn roll_back(wiki: &Wiki, term: &Term, globals: &GlobalOpts, pages: &[PageListItem], moves: &MoveSuccess) -> Result<()> {
+    if moves.moved.is_empty() {
+        term.write_line("No pages were moved, so there's nothing to roll back.")?;
+        return Ok(());
+    }
+    term.write_line(&format!("Rolling back: moving the {} pages which moved back to where they were.", moves.moved.len()))?;
+
+    let rollback = match wiki.rollback_moves(pages, moves).await {
+        Ok(rollback) | Err(WikiError::PartialMove(rollback)) => rollback,
+        Err(e) => {
+            term.write_line(&format!(
+                "Rolling back failed: {}. These pages are still at their new path:\n{}",
+                e,
+                moves.outcomes.iter().filter(|o| moves.moved.contains(&o.id)).map(|o| format!("{} (was {})", o.to, o.from)).join("\n")
+            ))?;
+            return Ok(());
+        }
+    };
+
+    let stuck = rollback.outcomes.iter().filter(|o| !rollback.moved.contains(&o.id)).collect::<Vec<_>>();
+    match &rollback.failures {
+        None if stuck.is_empty() => term.write_line(&format!("All {} moved pages have been moved back.", rollback.success_count))?,
+        failures => {
+            report_failures(
+                term,
+                globals.json_errors,
+                globals.verbose > 0,
+                &format!(
+                    "{} failures occurred while rolling back, on top of those in the move. {} pages were moved back.",
+                    stuck.len(),
+                    rollback.success_count
+                ),
+                failures.iter().flatten().map(|rs| (None, rs)),
+            )?;
+            term.write_line(&format!(
+                "These pages are still at their new path:\n{}",
+                stuck.iter().map(|o| format!("{} (was {})", o.from, o.to)).join("\n")
+            ))?;
+        }
+    }
+    Ok(())
+}
+
+/// Describe the pages which failed after `summary`, as prose on `term`, or
+/// with --json-errors as a JSON object on stderr. Known Wiki.js errors are
+/// explained, with the raw code added when `verbose`
+fn report_failures<'a>(
+    term: &Term,
+    json_errors: bool,
+    verbose: bool,
+    summary: &str,
+    fails: impl Iterator<Item = (Option<usize>, &'a ResponseStatus)>,
+) -> Result<()> {
+    if json_errors {
+        print_json_error(summary, fails.map(|(line, rs)| failure_json(line, rs)).collect());
+        return Ok(());
+    }
+
+    term.write_line(summary)?;
+    // the most common cause first
+    let fails = fails
+        .into_group_map_by(|(_, rs)| FailureCategory::of(rs))
+        .into_iter()
+        .sorted_by(|a, b| b.1.len().cmp(&a.1.len()).then(a.0.cmp(&b.0)))
+        .collect::<Vec<_>>();
+    term.write_line(&format!(
+        "By cause: {}",
+        fails.iter().map(|(category, fails)| format!("{} {}", fails.len(), category.name())).join(", ")
+    ))?;
+    let blank = String::new();
+    for (category, fails) in fails {
+        term.write_line(&format!("{} ({}):", category.name(), fails.len()))?;
+        term.write_line(
+            &fails
+                .into_iter()
+                .map(|(line, rs)| {
+                    let raw = format!(
+                        "Code: {} Slug: {} Message: {}",
+                        rs.error_code,
+                        rs.slug,
+                        &rs.message.as_ref().unwrap_or(&blank),
+                    );
+                    let failure = match (explain_failure(rs), verbose) {
+                        (Some(explanation), false) => explanation.to_owned(),
+                        (Some(explanation), true) => format!("{} ({})", explanation, raw),
+                        (None, _) => raw,
+                    };
+                    match line {
+                        Some(n) => format!("  Line: {} {}", n, failure),
+                        None => format!("  {}", failure),
+                    }
+                })
+                .join("\n"),
+        )?;
+    }
+    Ok(())
+}
+
+#[derive(Clone, Copy)]
+struct Styles {
+    scaffold: Style,
+    message: Style,
+    user: Style,
+    output: Style,
+}
+
+/// What a command says about its progress, as opposed to its results, which
+/// it prints itself. Chosen once from --quiet, --plain & --format
+trait Reporter {
+    /// [1/3] About to connect to the wiki
+    fn connecting(&self) -> Result<()>;
+    /// [2/3] Looking for `what`, e.g. "all pages beginning with", `subject`, with all of `tags` if any
+    fn finding(&self, what: &str, subject: &str, tags: Option<&[String]>) -> Result<()>;
+    /// [3/3] `doing` something to the `found` pages, out of those `returned` by the wiki if known
+    fn pages_found(&self, doing: &str, found: usize, what: &str, returned: Option<usize>) -> Result<()>;
+    /// Anything else worth knowing while a command works
+    fn note(&self, message: &str) -> Result<()>;
+    /// Something which may not be what was meant
+    fn warning(&self, message: &str) -> Result<()>;
+}
+
+/// Colored, numbered steps for people to read
+struct HumanReporter {
+    term: Term,
+    stream: Stream,
+    styles: Styles,
+    verbose: bool,
+}
+
+impl Reporter for HumanReporter {
+    fn connecting(&self) -> Result<()> {
+        self.term.write_line(&format!(
+            "{} {}  {}.",
+            "[1/3]".if_supports_color(self.stream, |text| text.style(self.styles.scaffold)),
+            Emoji("☎️", ""),
+            "Preparing to connect to the Wiki".if_supports_color(self.stream, |text| text.style(self.styles.message))
+        ))?;
+        Ok(())
+    }
+
+    fn finding(&self, what: &str, subject: &str, tags: Option<&[String]>) -> Result<()> {
+        self.term.write_line(&format!(
+            "{} {}  {} {}{}.",
+            "[2/3]".if_supports_color(self.stream, |text| text.style(self.styles.scaffold)),
+            Emoji("🔍", ""),
+            what.if_supports_color(self.stream, |text| text.style(self.styles.message)),
+            subject.if_supports_color(self.stream, |text| text.style(self.styles.user)),
+            match tags {
+                Some(tags) => format!(
+                    " {} {}",
+                    "which have the tags:".if_supports_color(self.stream, |text| text.style(self.styles.message)),
+                    tags.join(", ").if_supports_color(self.stream, |text| text.style(self.styles.user))
+                ),
+                None => String::new(),
+            }
+        ))?;
+        Ok(())
+    }
+
+    fn pages_found(&self, doing: &str, found: usize, what: &str, returned: Option<usize>) -> Result<()> {
+        self.term.write_line(&format!(
+            "{} {}  {} {} {}{}.",
+            "[3/3]".if_supports_color(self.stream, |text| text.style(self.styles.scaffold)),
+            Emoji("📝", ""),
+            doing.if_supports_color(self.stream, |text| text.style(self.styles.message)),
+            found.if_supports_color(self.stream, |text| text.style(self.styles.output)),
+            what.if_supports_color(self.stream, |text| text.style(self.styles.message)),
+            match (returned, self.verbose) {
+                (Some(returned), true) => format!(
+                    " {} {} {}",
+                    "out of".if_supports_color(self.stream, |text| text.style(self.styles.message)),
+                    returned.if_supports_color(self.stream, |text| text.style(self.styles.output)),
+                    "returned by wiki".if_supports_color(self.stream, |text| text.style(self.styles.message))
+                ),
+                _ => String::new(),
+            }
+        ))?;
+        Ok(())
+    }
+
+    fn note(&self, message: &str) -> Result<()> {
+        self.term.write_line(message)?;
+        Ok(())
+    }
+
+    fn warning(&self, message: &str) -> Result<()> {
+        Term::stderr().write_line(&format!(
+            "{} {}",
+            "Warning:".if_supports_color(Stream::Stderr, |text| text.style(Style::new().yellow().bold())),
+            message
+        ))?;
+        Ok(())
+    }
+}
+
+/// Says nothing but warnings, for --quiet & --plain
+struct QuietReporter;
+
+impl Reporter for QuietReporter {
+    fn connecting(&self) -> Result<()> {
+        Ok(())
+    }
+
+    fn finding(&self, _: &str, _: &str, _: Option<&[String]>) -> Result<()> {
+        Ok(())
+    }
+
+    fn pages_found(&self, _: &str, _: usize, _: &str, _: Option<usize>) -> Result<()> {
+        Ok(())
+    }
+
+    fn note(&self, _: &str) -> Result<()> {
+        Ok(())
+    }
+
+    fn warning(&self, message: &str) -> Result<()> {
+        Term::stderr().write_line(&format!("Warning: {}", message))?;
+        Ok(())
+    }
+}
+
+/// One JSON object per line on stderr, leaving stdout to --format json & ndjson
+struct JsonReporter;
+
+impl JsonReporter {
+    fn emit(&self, event: Value) -> Result<()> {
+        Term::stderr().write_line(&event.to_string())?;
+        Ok(())
+    }
+}
+
+impl Reporter for JsonReporter {
+    fn connecting(&self) -> Result<()> {
+        self.emit(json!({ "progress": "connecting" }))
+    }
+
+    fn finding(&self, _: &str, subject: &str, tags: Option<&[String]>) -> Result<()> {
+        self.emit(json!({ "progress": "finding", "from": subject, "tags": tags }))
+    }
+
+    fn pages_found(&self, _: &str, found: usize, _: &str, returned: Option<usize>) -> Result<()> {
+        self.emit(json!({ "progress": "found", "pages": found, "returned": returned }))
+    }
+
+    fn note(&self, message: &str) -> Result<()> {
+        self.emit(json!({ "note": message }))
+    }
+
+    fn warning(&self, message: &str) -> Result<()> {
+        self.emit(json!({ "warning": message }))
+    }
+}
+
+/// Make panic message more useful. human-panic 1.x's hook names std's deprecated `PanicInfo`
+#[allow(deprecated)]
+fn setup_panic() {
+    human_panic::setup_panic!();
+}
+
+#[tokio::main]
+async fn main() {
+    setup_panic();
+
+    // clap exits with 2 on usage errors, which would read as a partial failure
+    let app = match App::try_parse() {
+        Ok(app) => app,
+        Err(e) => {
+            let _ = e.print();
+            std::process::exit(match e.use_stderr() {
+                true => Exit::Error,
+                false => Exit::Success, // --help & --version
+            } as i32)
+        }
+    };
+
+    let json_errors = app.global_opts.json_errors;
+    let exit = match run(app).await {
+        Ok(exit) => exit,
+        Err(e) => {
+            match json_errors {
+                true => {
+                    let failures = match e.downcast_ref::<WikiError>() {
+                        Some(WikiError::PartialMove(ms)) => ms.failures.iter().flatten().map(|rs| failure_json(None, rs)).collect(),
+                        Some(WikiError::PartialCopy(cs)) => cs.failures.iter().flatten().map(|rs| failure_json(None, rs)).collect(),
+                        Some(WikiError::PartialDelete(ds)) => ds.failures.iter().flatten().map(|rs| failure_json(None, rs)).collect(),
+                        _ => Vec::new(),
+                    };
+                    print_json_error(&format!("{:#}", e), failures);
+                }
+                false => eprintln!("Error: {:?}", e),
+            }
+            match (e.downcast_ref::<Aborted>(), e.downcast_ref::<WikiError>()) {
+                (Some(_), _) => Exit::Aborted,
+                _ if e.is::<PrivatePagesFound>() => Exit::PrivatePages,
+                (_, Some(WikiError::PartialMove(_) | WikiError::PartialCopy(_) | WikiError::PartialDelete(_))) => Exit::PartialFailure,
+                _ => Exit::Error,
+            }
+        }
+    };
+
+    std::process::exit(exit as i32)
+}
+
+async fn run(app: App) -> Result<Exit> {
+    let mut cfg: WikcliConfig = match app.global_opts.config {
+        Some(ref p) => {confy::load_path(p)?}
+        None => {confy::load(env!("CARGO_PKG_NAME"))?}
+    };
+
+    if let Some(from) = cfg.migrate() {
+        match app.global_opts.migrate_config {
+            true => match app.global_opts.config {
+                Some(ref p) => {confy::store_path(p, &cfg)?;}
+                None => {confy::store(env!("CARGO_PKG_NAME"), &cfg)?;}
+            },
+            false => if app.global_opts.verbose > 0 {
+                eprintln!(
+                    "Config file is version {}, the current version is {}. Use --migrate-config to upgrade it.",
+                    from, CONFIG_VERSION
+                );
+            }
+        }
+    }
+
+    // Windows 10 Terminals can do ANSI colors with your help!
+    let color = match app.global_opts.no_color {
+        true => Color::Never,
+        false => app.global_opts.color,
+    };
+    if enable_ansi_support::enable_ansi_support().is_ok() {
+        color.init()
+    }
+
+    let term = Term::stdout();
+
+    let styles = Styles {
+        scaffold: Style::new().bright_white().on_black(),
+        message: Style::new().blue().on_black(),
+        user: Style::new()
+            .bg::<xterm::ElectricIndigo>()
+            .fg::<xterm::Copperfield>()
+            .underline(),
+        output: Style::new().fg::<xterm::ElectricIndigo>().on_bright_white(),
+    };
+
+
+    let reporter: Box<dyn Reporter> = match (app.global_opts.quiet || app.global_opts.plain, app.global_opts.format) {
+        (true, _) => Box::new(QuietReporter),
+        // stdout is kept for results, so they can be redirected on their own
+        (false, Format::Table | Format::Csv) => Box::new(HumanReporter {
+            term: Term::stderr(),
+            stream: Stream::Stderr,
+            styles,
+            verbose: app.global_opts.verbose > 0,
+        }),
+        (false, Format::Json | Format::Ndjson) => Box::new(JsonReporter),
+    };
+
+    let base_path = app.global_opts.base_path.clone().or_else(|| cfg.base_path.clone());
+    let base_path = base_path.as_deref();
+
+    let plain = app.global_opts.plain;
+    let template = match plain {
+        true => Some(PLAIN_TEMPLATE),
+        false => app.global_opts.template.as_deref(),
+    };
+    let max_path_width = app.global_opts.max_path_width.or_else(default_path_width);
+
+    let exit = match app.command {
+        Command::ShowConfig => {
+            let effective = effective_config(&cfg, &app.global_opts)?;
+            let on_off = |b: bool| if b { "on" } else { "off" };
+            let missing = "[missing]";
+
+            term.write_line(&format!(
+                "Config file\t{}",
+                match &app.global_opts.config {
+                    Some(p) => p.display().to_string(),
+                    None => "default location".to_owned(),
+                }
+            ))?;
+            term.write_line(&format!(
+                "Endpoint\t{}",
+                effective.endpoint.as_deref().unwrap_or(missing)
+            ))?;
+            // never print the key itself
+            let api_key_file = app.global_opts.api_key_file.as_ref().or(cfg.api_key_file.as_ref());
+            term.write_line(&format!(
+                "API key\t{}",
+                match (&effective.api_key, &app.global_opts.api_key, api_key_file) {
+                    (None, _, _) => missing.to_owned(),
+                    (Some(_), None, Some(file)) => format!("set, from {}", file.display()),
+                    (Some(_), _, _) => "set".to_owned(),
+                }
+            ))?;
+            term.write_line(&format!("HTTP2 prior knowledge\t{}", on_off(effective.http2)))?;
+            term.write_line(&format!("Force HTTPS\t{}", on_off(effective.https)))?;
+            term.write_line(&format!("Dump queries\t{}", on_off(effective.dump_query)))?;
+            term.write_line(&format!(
+                "Rate\t{}",
+                match effective.rate {
+                    Some(r) => format!("{} requests/s", r),
+                    None => "unlimited".to_owned(),
+                }
+            ))?;
+            term.write_line(&format!("Base path\t{}", base_path.unwrap_or("")))?;
+            term.write_line(&format!("Locale\t{}", effective.locale))?;
+            term.write_line(&format!("Default tags\t{}", cfg.tags.as_deref().unwrap_or_default().join(", ")))?;
+            term.write_line(&format!(
+                "Max pages\t{}",
+                cfg.max_pages.unwrap_or(DEFAULT_MAX_PAGES)
+            ))?;
+            term.write_line(&format!("Confirm threshold\t{}", cfg.confirm_threshold.unwrap_or(0)))?;
+            term.write_line(&format!("System pages\t{}", system_pages(&cfg).join(", ")))?;
+            term.write_line(&format!("Sensitive markers\t{}", sensitive_markers(&cfg, &app.global_opts).join(", ")))?;
+
+            match (effective.api_key, effective.endpoint) {
+                (Some(_), Some(_)) => Exit::Success,
+                _ => {
+                    term.write_line("Set the missing values with flags, or `wiki config`.")?;
+                    Exit::Error
+                }
+            }
+        }
+        Command::Config { output, interactive, verify, set } => {
+            if !set.is_empty() {
+                for assignment in &set {
+                    cfg.set(assignment)?;
+                }
+
+                if verify {
+                    let wiki = Wiki::new(wiki_config(&cfg, &app.global_opts)?);
+                    let title = wiki.get_wiki_title().await
+                        .context("Could not connect to the wiki, the config file has not been written")?;
+                    term.write_line(&format!("Successfully connected to wiki: {}", title))?;
+                }
+
+                // write back to the file which was read, unless told otherwise
+                match output.as_ref().or(app.global_opts.config.as_ref()) {
+                    Some(p) => {confy::store_path(p, &cfg)?;},
+                    None => {confy::store(env!("CARGO_PKG_NAME"), &cfg)?;}
+                }
+                return Ok(Exit::Success);
+            }
+
+            // a key file is recorded instead of the key in it
+            let api_key_file = app.global_opts.api_key_file.clone().or_else(|| cfg.api_key_file.clone());
+            let api_key = match &app.global_opts.api_key {
+                Some(k) => Some(k.clone()),
+                None if api_key_file.is_some() => None,
+                None => match interactive {
+                    false => None,
+                    true => {Some(dialoguer::Password::new()
+                        .with_prompt("Enter your API Key: ")
+                        .interact()?)}
+                }
             };
 
-            let endpoint = match &app.global_opts.endpoint {
+            let endpoint = match app.global_opts.endpoint.as_ref().or(app.global_opts.wiki_url.as_ref()) {
                 Some(k) => Some(k.clone()),
                 None => match interactive {
                     false => None,
-                    true => {Some(dialoguer::Input::new()
-                        .with_prompt("Enter your API Endpoint: ")
+                    true => {
+                        let url: String = dialoguer::Input::new()
+                            .with_prompt("Enter your wiki's address, e.g. https://wiki.example.com: ")
+                            .validate_with(|u: &String| endpoint_from_url(u).map(|_| ()).map_err(|e| e.to_string()))
+                            .interact()?;
+                        let endpoint = endpoint_from_url(&url)?;
+                        term.write_line(&format!("Using the API endpoint {}", endpoint))?;
+                        Some(endpoint)
+                    }
+                }
+            };
+
+            let no_http2_prior_knowledge = match (app.global_opts.http2_prior_knowledge, app.global_opts.no_http2_prior_knowledge) {
+                (true, _) => Some(false),
+                (_, true) => Some(true),
+                _ => match interactive {
+                    false => None,
+                    true => {Some(!dialoguer::Confirm::new()
+                        .with_prompt("Do you want to presume HTTP2 Support? Say no if anything in front of the wiki only speaks HTTP/1 ")
                         .interact()?)}
                 }
-            };
-
-            let no_http2_prior_knowledge = match app.global_opts.no_http2_prior_knowledge {
-                true => Some(true),
-                false => match interactive {
-                    false => None,
-                    true => {Some(!dialoguer::Confirm::new()
-                        .with_prompt("Do you want to presume HTTP2 Support? ")
-                        .interact()?)}
+            };
+
+            let no_force_https = match app.global_opts.no_force_https {
+                true => Some(true),
+                false => match interactive {
+                    false => None,
+                    true => {Some(!dialoguer::Confirm::new()
+                        .with_prompt("Do you want to force HTTPS? ")
+                        .interact()?)}
+                }
+            };
+
+            let new_cfg= WikcliConfig {
+                version: Some(CONFIG_VERSION),
+                api_key,
+                api_key_file,
+                endpoint,
+                no_http2_prior_knowledge,
+                no_force_https,
+                max_pages: cfg.max_pages,
+                base_path: cfg.base_path.clone(),
+                confirm_threshold: cfg.confirm_threshold,
+                tags: cfg.tags.clone(),
+                system_pages: cfg.system_pages.clone(),
+                locale: app.global_opts.locale.clone().or_else(|| cfg.locale.clone()),
+                sensitive_markers: app.global_opts.sensitive_markers.clone().or_else(|| cfg.sensitive_markers.clone()),
+            };
+
+            let test_config = match (verify, interactive) {
+                (true, _) => true,
+                (false, true) => dialoguer::Confirm::new()
+                    .with_prompt("Do you want to test this config now? ")
+                    .interact()?,
+                (false, false) => false,
+            };
+            if test_config {
+                let wiki = Wiki::new(wiki_config(&new_cfg, &app.global_opts)?);
+                let title = wiki.get_wiki_title().await
+                    .context("Could not connect to the wiki, the config file has not been written")?;
+                term.write_line(&format!("Successfully connected to wiki: {}", title))?;
+            }
+
+            match output {
+                Some(ref p) => {confy::store_path(p, new_cfg)?;},
+                None => {confy::store(env!("CARGO_PKG_NAME"), new_cfg)?;}
+            }
+            Exit::Success
+        }
+        Command::List { path, prefix_file, tags, since, editor, only_untitled, locale_filter, author, creator, content_contains, content_regex, order_by, direction, reverse, limit, offset, group_by_tag, count, no_sort, watch, fields } => {
+            let tags = tags_or_default(tags, &cfg);
+            if fields.is_some() && app.global_opts.template.is_some() {
+                bail!("--fields can't be used with --template, which already chooses what's listed.")
+            }
+            // the chosen fields are listed through a template of their own
+            let fields_template = fields.as_ref().map(|fields| fields.iter().map(|f| f.placeholder()).join("\t"));
+            let template = fields_template.as_deref().or(template);
+            let fields_header = fields.as_ref().filter(|_| !plain).map(|fields| fields.iter().map(|f| f.heading()).join("\t"));
+            // stdout is kept for the pages alone
+            let ndjson = app.global_opts.format == Format::Ndjson;
+            let json_array = app.global_opts.format == Format::Json;
+            let csv_fields = match app.global_opts.format {
+                Format::Csv => Some(fields.as_deref().unwrap_or(&CSV_FIELDS)),
+                _ => None,
+            };
+            let prefixes = match &prefix_file {
+                Some(file) => std::fs::read_to_string(file)
+                    .with_context(|| format!("Could not read prefix file {}", file.display()))?
+                    .lines()
+                    .map(str::trim)
+                    .filter(|l| !l.is_empty())
+                    .map(|l| resolve_path(base_path, l))
+                    .unique()
+                    .collect::<Vec<_>>(),
+                None => Vec::new(),
+            };
+            // with a prefix file every page is fetched, and paths are shown in full
+            let path = match path {
+                Some(path) => resolve_path(base_path, &path),
+                None => String::new(),
+            };
+
+            // a count is all that's printed
+            let reporter = match count {
+                true => &QuietReporter,
+                false => reporter.as_ref(),
+            };
+
+            reporter.connecting()?;
+
+            // checked & announced alongside the first listing, to save a round trip
+            let wiki = Wiki::new(wiki_config(&cfg, &app.global_opts)?);
+
+            reporter.finding(
+                "Finding all pages beginning with",
+                &match &prefix_file {
+                    Some(file) => format!("any prefix in {}", file.display()),
+                    None => path.clone(),
+                },
+                tags.as_deref(),
+            )?;
+            let trim = path.clone(); // keep for string trimming later
+
+            let hint = tag_mode_hint(tags.as_ref(), app.global_opts.tag_mode);
+            let (tags, any_tags) = app.global_opts.tag_mode.split(tags);
+            let descending = reverse || direction == Some(Direction::Desc);
+            let server_order = order_by.and_then(PageSort::server_order);
+            // the wiki can only stop at `limit` pages if none are filtered out afterwards
+            let filtered_here = !path.is_empty()
+                || !prefixes.is_empty()
+                || any_tags.is_some()
+                || since.is_some()
+                || editor.is_some()
+                || only_untitled
+                || content_contains.is_some()
+                || content_regex.is_some();
+            // locale and users are filtered by the wiki, the rest here. Only an explicit
+            // --locale filters a listing, not the one in the config
+            let filter = ListAllPagesArguments {
+                tags,
+                locale: locale_filter.or_else(|| app.global_opts.locale.clone()),
+                author_id: author,
+                creator_id: creator,
+                order_by: server_order,
+                order_by_direction: server_order.map(|_| match descending {
+                    true => PageOrderByDirection::Desc,
+                    false => PageOrderByDirection::Asc,
+                }),
+                // skipped pages are still returned by the wiki, so it's asked for those too
+                limit: match (server_order, filtered_here) {
+                    (Some(_), false) => limit.map(|n| (n + offset.unwrap_or(0)).try_into().unwrap_or(i32::MAX)),
+                    _ => None,
+                },
+                // with --fields only those chosen are asked for, and any the filters here need
+                fields: match &fields {
+                    Some(fields) => ListFields {
+                        locale: fields.contains(&Field::Locale),
+                        tags: fields.contains(&Field::Tags) || any_tags.is_some() || group_by_tag,
+                        title: fields.contains(&Field::Title) || only_untitled,
+                        content_type: fields.contains(&Field::Editor) || editor.is_some(),
+                        created_at: fields.contains(&Field::Created),
+                        updated_at: fields.contains(&Field::Updated) || since.is_some() || watch.is_some(),
+                    },
+                    None => ListFields::ALL,
+                },
+            };
+            let wanted = |p: &PageListItem| {
+                (prefixes.is_empty() || prefixes.iter().any(|prefix| p.path.starts_with(prefix.as_str())))
+                    && any_tags.as_ref().is_none_or(|t| has_any_tag(p, t))
+                    && since.is_none_or(|since| p.updated_at >= since)
+                    && editor.as_ref().is_none_or(|e| p.content_type.eq_ignore_ascii_case(e))
+                    && (!only_untitled || is_untitled(p))
+            };
+
+            if let Some(every) = watch {
+                if every.is_zero() {
+                    bail!("--watch needs a number of seconds greater than 0.")
+                }
+                // a watch never ends, so even --format json prints a line per page
+                let row = |change: &str, p: &PageListItem| match (ndjson || json_array, csv_fields) {
+                    (true, _) => {
+                        let mut page = select_fields(page_json(p, None), fields.as_deref());
+                        page["change"] = json!(change);
+                        page.to_string()
+                    }
+                    (false, Some(csv_fields)) => format!("{},{}", change, page_csv(p, csv_fields, None)),
+                    // paths are printed in full, as with --no-sort
+                    (false, None) => format!(
+                        "{}\t{}",
+                        change,
+                        format_pages([p], Some(template.unwrap_or(DEFAULT_TEMPLATE)), &trim, None).concat()
+                    ),
+                };
+                check_and_announce(&wiki, &app.global_opts).await;
+                let mut previous: Option<std::collections::HashMap<i32, PageListItem>> = None;
+                loop {
+                    match wiki.list_pages_where(&path, filter.clone()).await {
+                        Ok(ListPages { pages, .. }) => {
+                            let current = pages.into_iter().filter(|p| wanted(p)).map(|p| (p.id, p)).collect::<std::collections::HashMap<_, _>>();
+                            match &previous {
+                                None => {
+                                    if let Some(csv_fields) = csv_fields {
+                                        term.write_line(&format!("change,{}", csv_header(csv_fields, false)))?;
+                                    }
+                                    for p in current.values().sorted_by(|a, b| a.path.cmp(&b.path)) {
+                                        term.write_line(&row("listed", p))?;
+                                    }
+                                    if !(ndjson || json_array) {
+                                        Term::stderr().write_line(&format!(
+                                            "Watching {} pages, checking every {}s. Press Ctrl-C to stop.",
+                                            current.len(),
+                                            every.as_secs_f64()
+                                        ))?;
+                                    }
+                                }
+                                Some(previous) => {
+                                    let added = current.values().filter(|p| !previous.contains_key(&p.id)).map(|p| ("added", p));
+                                    let removed = previous.values().filter(|p| !current.contains_key(&p.id)).map(|p| ("removed", p));
+                                    let changed = current.values()
+                                        .filter(|p| previous.get(&p.id).is_some_and(|old| old.updated_at != p.updated_at || old.path != p.path))
+                                        .map(|p| ("changed", p));
+                                    for (change, p) in added.chain(removed).chain(changed).sorted_by(|a, b| a.1.path.cmp(&b.1.path)) {
+                                        term.write_line(&row(change, p))?;
+                                    }
+                                }
+                            }
+                            previous = Some(current);
+                        }
+                        // keep watching through a wiki which is briefly unreachable
+                        Err(e) => Term::stderr().write_line(&format!("Could not list pages, trying again in {}s: {}", every.as_secs_f64(), e))?,
+                    }
+                    // waiting out the interval, unless Ctrl-C is pressed first
+                    if tokio::time::timeout(every, tokio::signal::ctrl_c()).await.is_ok() {
+                        return Ok(Exit::Success);
+                    }
+                }
+            }
+
+            if no_sort {
+                if let Some(csv_fields) = csv_fields {
+                    term.write_line(&csv_header(csv_fields, false))?;
+                }
+                match (template, app.global_opts.verbose) {
+                    _ if ndjson || json_array || csv_fields.is_some() => {}
+                    (Some(_), _) => if let Some(header) = &fields_header {
+                        term.write_line(&format!(
+                            "{}",
+                            header.if_supports_color(Stream::Stdout, |text| text.style(styles.message))
+                        ))?;
+                    }
+                    (None, 0) => term.write_line(&format!(
+                        "{}",
+                        HEADER.if_supports_color(Stream::Stdout, |text| text.style(styles.message))
+                    ))?,
+                    (None, _) => term.write_line(&format!(
+                        "{}",
+                        format!("{}\tLocale\tEditor\tCreated\tUpdated", HEADER)
+                            .if_supports_color(Stream::Stdout, |text| text.style(styles.message))
+                    ))?,
+                }
+                check_and_announce(&wiki, &app.global_opts).await;
+                let pages = match app.global_opts.cache || app.global_opts.refresh {
+                    true => {
+                        let cached = list_pages_cached(&wiki, &app.global_opts, &path, filter).await?.pages;
+                        futures::StreamExt::left_stream(futures::stream::iter(cached.into_iter().map(Ok)))
+                    }
+                    false => futures::StreamExt::right_stream(wiki.stream_pages(&path, filter)),
+                }
+                .try_filter(|p| futures::future::ready(wanted(p)));
+                futures::pin_mut!(pages);
+                // an array can only be printed once it's complete
+                let mut listed = Vec::new();
+                while let Some(p) = pages.try_next().await? {
+                    if ndjson {
+                        term.write_line(&select_fields(page_json(&p, None), fields.as_deref()).to_string())?;
+                        continue;
+                    }
+                    if json_array {
+                        listed.push(select_fields(page_json(&p, None), fields.as_deref()));
+                        continue;
+                    }
+                    if let Some(csv_fields) = csv_fields {
+                        term.write_line(&page_csv(&p, csv_fields, None))?;
+                        continue;
+                    }
+                    // always a template, so paths are printed in full rather than padded
+                    let line = format_pages([&p], Some(template.unwrap_or(DEFAULT_TEMPLATE)), &trim, None).concat();
+                    term.write_line(&match (template, app.global_opts.verbose) {
+                        (None, v) if v > 0 => format!("{}{}", line, verbose_columns(&p)),
+                        _ => line,
+                    })?;
+                }
+                if json_array {
+                    term.write_line(&cynic::serde_json::to_string_pretty(&listed)?)?;
+                }
+                return Ok(Exit::Success);
+            }
+
+            let ((), pages) = tokio::join!(
+                check_and_announce(&wiki, &app.global_opts),
+                list_pages_cached(&wiki, &app.global_opts, &path, filter)
+            );
+            let mut pages = pages?;
+            pages.pages.retain(wanted);
+
+            // the first matching line of each page, shown with -v
+            let mut snippets = std::collections::HashMap::new();
+            let searched = content_contains.is_some() || content_regex.is_some();
+            if searched {
+                Term::stderr().write_line(&format!(
+                    "Searching the content of {} pages, one request each. This may take a while.",
+                    pages.pages.len()
+                ))?;
+                let find = |content: &str| matching_line(content, content_contains.as_deref(), content_regex.as_ref());
+                snippets = wiki.pages_where(pages.pages.iter(), find).await?
+                    .into_iter()
+                    .map(|(p, line)| (p.id, line))
+                    .collect();
+                pages.pages.retain(|p| snippets.contains_key(&p.id));
+            }
+
+            reporter.pages_found("Formatting", pages.pages.len(), "matching pages", Some(pages.pages_returned))?;
+
+            // the wiki's pages already come sorted by path, or in the order asked of it
+            match (order_by, server_order) {
+                (_, Some(_)) => {}
+                (Some(PageSort::Depth), _) => pages.pages.sort_by(|a, b| {
+                    let depth = |p: &PageListItem| p.path.matches('/').count();
+                    let by_depth = depth(a).cmp(&depth(b));
+                    match descending {
+                        true => by_depth.reverse(),
+                        false => by_depth,
+                    }
+                    .then_with(|| a.path.cmp(&b.path))
+                }),
+                _ if descending => pages.pages.reverse(),
+                _ => {}
+            }
+            if let Some(offset) = offset {
+                pages.pages.drain(..offset.min(pages.pages.len()));
+            }
+            if let Some(limit) = limit {
+                pages.pages.truncate(limit);
+            }
+
+            if count {
+                term.write_line(&match app.global_opts.verbose {
+                    0 => format!("{}", pages.pages.len()),
+                    _ => format!("{}\t{}", pages.pages.len(), pages.pages_returned),
+                })?;
+                return Ok(Exit::Success);
+            }
+
+            if ndjson {
+                for p in &pages.pages {
+                    term.write_line(&select_fields(page_json(p, snippets.get(&p.id)), fields.as_deref()).to_string())?;
+                }
+                return Ok(Exit::Success);
+            }
+
+            if json_array {
+                let listed = pages
+                    .pages
+                    .iter()
+                    .map(|p| select_fields(page_json(p, snippets.get(&p.id)), fields.as_deref()))
+                    .collect::<Vec<_>>();
+                term.write_line(&cynic::serde_json::to_string_pretty(&listed)?)?;
+                return Ok(Exit::Success);
+            }
+
+            if let Some(csv_fields) = csv_fields {
+                term.write_line(&csv_header(csv_fields, searched))?;
+                for p in &pages.pages {
+                    term.write_line(&page_csv(p, csv_fields, snippets.get(&p.id)))?;
+                }
+                return Ok(Exit::Success);
+            }
+
+            if let Some(message) = nothing_matched(&pages.pages, &path, None, hint) {
+                term.write_line(&message)?;
+                return Ok(Exit::Success);
+            }
+
+            if app.global_opts.verbose > 0 && !prefixes.is_empty() {
+                term.write_line(
+                    &prefixes
+                        .iter()
+                        .map(|prefix| {
+                            format!(
+                                "{}\t{} pages",
+                                prefix,
+                                pages.pages.iter().filter(|p| p.path.starts_with(prefix.as_str())).count()
+                            )
+                        })
+                        .join("\n"),
+                )?;
+            }
+
+            // grouped, a page is listed under each of its tags
+            let groups = match group_by_tag {
+                false => vec![(None, pages.pages.iter().collect::<Vec<_>>())],
+                true => group_by_tags(&pages.pages)
+                    .into_iter()
+                    .map(|(tag, group)| (Some(tag), group))
+                    .collect(),
+            };
+
+            match (template, app.global_opts.verbose) {
+                (Some(_), _) => if let Some(header) = &fields_header {
+                    term.write_line(&format!(
+                        "{}",
+                        header.if_supports_color(Stream::Stdout, |text| text.style(styles.message))
+                    ))?;
+                }
+                (None, 0) => {
+                    term.write_line(&format!(
+                        "{}",
+                        HEADER.if_supports_color(Stream::Stdout, |text| text.style(styles.message))
+                    ))?;
+                }
+                (None, _) => {
+                    let columns = match searched {
+                        true => format!("{}\tLocale\tEditor\tCreated\tUpdated\tMatch", HEADER),
+                        false => format!("{}\tLocale\tEditor\tCreated\tUpdated", HEADER),
+                    };
+                    term.write_line(&format!(
+                        "{}",
+                        columns.if_supports_color(Stream::Stdout, |text| text.style(styles.message))
+                    ))?;
+                }
+            }
+
+            for (tag, group) in groups {
+                if let Some(tag) = tag {
+                    term.write_line(&format!(
+                        "\n{}",
+                        tag.if_supports_color(Stream::Stdout, |text| text.style(styles.scaffold))
+                    ))?;
+                }
+                let lines = format_pages(group.iter().copied(), template, &trim, max_path_width);
+                term.write_line(&match (template, app.global_opts.verbose) {
+                    (None, v) if v > 0 => lines
+                        .into_iter()
+                        .zip(group)
+                        .map(|(line, p)| match snippets.get(&p.id) {
+                            Some(snippet) => format!("{}{}\t{}", line, verbose_columns(p), snippet),
+                            None => format!("{}{}", line, verbose_columns(p)),
+                        })
+                        .join("\n"),
+                    _ => lines.join("\n"),
+                })?;
+            }
+            Exit::Success
+        }
+        Command::Batch { file, strict, yes, force, max_pages, include_system_pages } => {
+            let contents = std::fs::read_to_string(&file)
+                .with_context(|| format!("Could not read batch file {}", file.display()))?;
+            let (entries, malformed) = parse_batch(&contents);
+            let entries = entries
+                .into_iter()
+                .map(|(n, prefix, destination)| {
+                    (n, resolve_path(base_path, &prefix), resolve_path(base_path, &destination))
+                })
+                .collect::<Vec<_>>();
+
+            if !malformed.is_empty() {
+                term.write_line(&format!(
+                    "The following lines are not `prefix,destination` pairs:\n{}",
+                    malformed.iter().map(|(n, l)| format!("line {}: {}", n, l)).join("\n")
+                ))?;
+                if strict {
+                    bail!("{} malformed lines in {}.", malformed.len(), file.display())
+                }
+            }
+
+            reporter.connecting()?;
+
+            let wiki = connect(&cfg, &app.global_opts).await?;
+
+            reporter.finding("Finding pages for", &format!("{} moves", entries.len()), None)?;
+
+            // one listing, shared out between the lines by prefix
+            let ListPages { mut pages, .. } = wiki.list_pages("", None).await?;
+
+            if !include_system_pages {
+                let system = system_pages(&cfg);
+                let (kept, left): (Vec<_>, Vec<_>) = pages.into_iter().partition(|p| !is_system_page(p, &system));
+                pages = kept;
+                if !left.is_empty() {
+                    reporter.note(&format!(
+                        "Leaving {} system pages where they are, add --include-system-pages to move them too: {}",
+                        left.len(),
+                        left.iter().map(|p| &p.path).join(", ")
+                    ))?;
+                }
+            }
+
+            let batch = entries
+                .into_iter()
+                .map(|(n, prefix, destination)| {
+                    let matched = pages.iter().filter(|p| p.path.starts_with(prefix.as_str())).cloned().collect::<Vec<_>>();
+                    (n, prefix, destination, matched)
+                })
+                .collect::<Vec<_>>();
+
+            reporter.pages_found("Formatting", batch.len(), "moves", None)?;
+
+            let total = batch.iter().map(|(_, _, _, pages)| pages.len()).sum::<usize>();
+            let max_pages = max_pages.or(cfg.max_pages).unwrap_or(DEFAULT_MAX_PAGES);
+            if total > max_pages && !force {
+                bail!(
+                    "{} pages match across the batch, which is more than the limit of {}. Use --force, or raise --max-pages, if you really mean to move them all.",
+                    total,
+                    max_pages
+                )
+            }
+
+            term.write_line("Line\tPages\tMove")?;
+            term.write_line(
+                &batch
+                    .iter()
+                    .map(|(n, prefix, destination, pages)| {
+                        format!("{}\t{}\t{}… -> {}…", n, pages.len(), prefix, destination)
+                    })
+                    .join("\n"),
+            )?;
+
+            let private_pages = wiki.safety_check_private(batch.iter().flat_map(|(_, _, _, pages)| pages));
+
+            let checks = PageChecks {
+                action: &MOVING,
+                term: &term,
+                reporter: reporter.as_ref(),
+                cfg: &cfg,
+                template,
+                max_path_width,
+            };
+            checks.confirm(yes, &private_pages, "")?;
+
+            let mut success_count = 0;
+            let mut failures = Vec::new();
+            for (n, prefix, destination, pages) in &batch {
+                let to = Destination::Prefix { prefix: prefix.clone(), destination: destination.clone(), as_folder: true };
+                // a line which partly failed is reported with the rest, only other errors stop the batch
+                let moves = match wiki.move_pages(pages, &to, None, false, None, std::future::pending()).await {
+                    Ok(moves) | Err(WikiError::PartialMove(moves)) => moves,
+                    Err(e) => return Err(anyhow::Error::new(e).context(format!(
+                        "Moving line {} ({} -> {}) failed, after {} pages were moved",
+                        n, prefix, destination, success_count
+                    ))),
+                };
+                success_count += moves.success_count;
+                failures.extend(moves.failures.into_iter().flatten().map(|rs| (*n, rs)));
+            }
+            let failures = match failures.len() {
+                0 => None,
+                _ => Some(failures),
+            };
+            let exit = Exit::from_failures(success_count, &failures);
+
+            match failures {
+                None => {
+                    term.write_line(&format!(
+                        "All {} pages have been moved successfully.",
+                        success_count
+                    ))?;
+                }
+                Some(fails) => {
+                    report_failures(
+                        &term,
+                        app.global_opts.json_errors,
+                        app.global_opts.verbose > 0,
+                        &format!(
+                            "{} failures occurred during moves. {} successes occurred. Pages may be inconsistently moved.",
+                            fails.len(),
+                            success_count
+                        ),
+                        fails.iter().map(|(n, rs)| (Some(*n), rs)),
+                    )?;
+                }
+            }
+            exit
+        }
+        Command::Stats { path, tags } => {
+            let path = resolve_path(base_path, &path);
+            let tags = tags_or_default(tags, &cfg);
+
+            reporter.connecting()?;
+
+            let wiki = connect(&cfg, &app.global_opts).await?;
+
+            reporter.finding("Finding all pages beginning with", &path, tags.as_deref())?;
+
+            let (tags, any_tags) = app.global_opts.tag_mode.split(tags);
+            let filter = ListAllPagesArguments {
+                tags,
+                locale: None,
+                author_id: None,
+                creator_id: None,
+                order_by: None,
+                order_by_direction: None,
+                limit: None,
+                fields: ListFields::ALL,
+            };
+            let ListPages { mut pages, .. } = list_pages_cached(&wiki, &app.global_opts, &path, filter).await?;
+            if let Some(any_tags) = any_tags {
+                pages.retain(|p| has_any_tag(p, &any_tags));
+            }
+
+            reporter.pages_found("Summarising", pages.len(), "matching pages", None)?;
+
+            let untitled = pages
+                .iter()
+                .filter(|p| is_untitled(p))
+                .count();
+            let by_editor = pages
+                .iter()
+                .map(|p| p.content_type.as_str())
+                .counts()
+                .into_iter()
+                .collect::<std::collections::BTreeMap<_, _>>();
+            let by_tag = pages
+                .iter()
+                .flat_map(|p| p.tags.iter().flatten().flatten())
+                .map(String::as_str)
+                .counts()
+                .into_iter()
+                .collect::<std::collections::BTreeMap<_, _>>();
+
+            match app.global_opts.format {
+                Format::Json | Format::Ndjson => {
+                    term.write_line(&json!({
+                        "pages": pages.len(),
+                        "untitled": untitled,
+                        "editors": by_editor,
+                        "tags": by_tag,
+                    }).to_string())?;
+                }
+                Format::Table | Format::Csv => {
+                    term.write_line(&format!("Pages\t{}\nUntitled\t{}", pages.len(), untitled))?;
+                    term.write_line("\nEditor\tPages")?;
+                    term.write_line(&by_editor.iter().map(|(e, n)| format!("{}\t{}", e, n)).join("\n"))?;
+                    term.write_line("\nTag\tPages")?;
+                    term.write_line(&by_tag.iter().map(|(t, n)| format!("{}\t{}", t, n)).join("\n"))?;
                 }
+            }
+            Exit::Success
+        }
+        Command::Orphans { path } => {
+            let path = resolve_path(base_path, &path);
+
+            reporter.connecting()?;
+
+            let wiki = connect(&cfg, &app.global_opts).await?;
+
+            reporter.finding("Finding the pages no page links to, beginning with", &path, None)?;
+            let trim = path.clone(); // keep for string trimming later
+
+            let ListPages {
+                pages,
+                pages_returned,
+            } = wiki.list_orphaned_pages(&path).await?;
+
+            reporter.pages_found("Formatting", pages.len(), "orphaned pages", Some(pages_returned))?;
+
+            if template.is_none() {
+                term.write_line(HEADER)?;
+            }
+            term.write_line(&format_pages(&pages, template, &trim, max_path_width).join("\n"))?;
+            Exit::Success
+        }
+        Command::Edit { id, file } => {
+            let content = match file {
+                Some(file) if file.as_os_str() != "-" => std::fs::read_to_string(&file)
+                    .with_context(|| format!("Could not read {}", file.display()))?,
+                _ => std::io::read_to_string(std::io::stdin())
+                    .context("Could not read content from stdin")?,
             };
 
-            let no_force_https = match app.global_opts.no_force_https {
-                true => Some(true),
-                false => match interactive {
-                    false => None,
-                    true => {Some(!dialoguer::Confirm::new()
-                        .with_prompt("Do you want to force HTTPS? ")
-                        .interact()?)}
+            let wiki = connect(&cfg, &app.global_opts).await?;
+            let status = wiki.update_page_content(id, content).await?;
+
+            match status.succeeded {
+                true => {
+                    term.write_line(&format!("Page {} has been updated.", id))?;
+                    Exit::Success
+                }
+                false => {
+                    term.write_line(&format!(
+                        "Updating page {} failed. Code: {} Slug: {} Message: {}",
+                        id,
+                        status.error_code,
+                        status.slug,
+                        status.message.unwrap_or_default(),
+                    ))?;
+                    Exit::AllFailed
                 }
+            }
+        }
+        Command::Bench { requests } => {
+            let limit = match app.global_opts.concurrency.unwrap_or_default() {
+                Concurrency::Fixed(limit) => limit,
+                Concurrency::Auto { .. } => bail!("bench needs a number of requests to send at once, e.g. --concurrency 8, rather than auto."),
             };
+            if requests == 0 {
+                bail!("Give bench at least one request to send.")
+            }
 
-            let new_cfg= WikcliConfig {
-                api_key,
-                endpoint,
-                no_http2_prior_knowledge,
-                no_force_https
-            };
+            let wiki = connect(&cfg, &app.global_opts).await?;
 
-            if interactive {
-                let test_config = dialoguer::Confirm::new()
-                    .with_prompt("Do you want to test this config now? ")
-                    .interact()?;
-                if test_config {
-                    let wiki = Wiki::new(wiki_config(&new_cfg, &app.global_opts)?);
-                    let title = wiki.get_wiki_title().await?;
-                    term.write_line(&format!("Successfully connected to wiki: {}", title))?;
+            reporter.note(&format!("Sending {} requests for the wiki's title, {} at a time.", requests, limit))?;
+            let started = std::time::Instant::now();
+            let results = wiki.time_requests(requests, limit).await;
+            let elapsed = started.elapsed();
+
+            let (latencies, mut errors): (Vec<_>, Vec<_>) = results.into_iter().partition_result();
+            if latencies.is_empty() {
+                return Err(errors.remove(0).into());
+            }
+            let failed = errors.len();
+            let latency = LatencySummary::of(&latencies);
+            let throughput = latencies.len() as f64 / elapsed.as_secs_f64();
+
+            match app.global_opts.format {
+                Format::Json | Format::Ndjson => {
+                    term.write_line(&json!({
+                        "requests": requests,
+                        "failed": failed,
+                        "concurrency": limit,
+                        "latency": latency.json(),
+                        "requests_per_second": throughput,
+                    }).to_string())?;
+                }
+                Format::Table | Format::Csv => {
+                    term.write_line(&format!("Requests\t{}\nFailed\t{}\nAt once\t{}", requests, failed, limit))?;
+                    term.write_line(&format!("Latency\t{}", latency))?;
+                    term.write_line(&format!("Throughput\t{:.1} requests/s", throughput))?;
                 }
             }
-
-            match output {
-                Some(ref p) => {confy::store_path(p, new_cfg)?;},
-                None => {confy::store(env!("CARGO_PKG_NAME"), new_cfg)?;}
+            match errors.into_iter().next() {
+                None => Exit::Success,
+                Some(e) => {
+                    reporter.warning(&format!("{} requests failed, the first with: {:#}", failed, anyhow::Error::new(e)))?;
+                    Exit::PartialFailure
+                }
             }
         }
-        Command::List { path, tags } => {
-            term.write_line(&format!(
-                "{} {}  {}.",
-                "[1/3]".if_supports_color(Stream::Stdout, |text| text.style(styles.scaffold)),
-                Emoji("☎️", ""),
-                "Preparing to connect to the Wiki"
-                    .if_supports_color(Stream::Stdout, |text| text.style(styles.message))
-            ))?;
+        Command::Tags { sort } => {
+            let wiki = connect(&cfg, &app.global_opts).await?;
+            let tags = wiki.list_tags().await?;
+            let ListPages { pages, .. } = wiki.list_pages("", None).await?;
 
-            let wiki = Wiki::new(wiki_config(&cfg, &app.global_opts)?);
+            let counts = pages
+                .iter()
+                .flat_map(|p| p.tags.iter().flatten().flatten())
+                .map(String::as_str)
+                .counts();
 
-            term.write_line(&format!(
-                "{} {}  {} {} {}.",
-                "[2/3]".if_supports_color(Stream::Stdout, |text| text.style(styles.scaffold)),
-                Emoji("🔍", ""),
-                "Finding all pages beginning with"
-                    .if_supports_color(Stream::Stdout, |text| text.style(styles.message)),
-                &path.if_supports_color(Stream::Stdout, |text| text.style(styles.user)),
-                match &tags {
-                    Some(tags) => format!(
-                        "{} {}",
-                        "which have the tags:"
-                            .if_supports_color(Stream::Stdout, |text| text.style(styles.message)),
-                        &tags
-                            .join(", ")
-                            .if_supports_color(Stream::Stdout, |text| text.style(styles.user))
-                    ),
-                    None => String::new(),
-                }
-            ))?;
-            let trim = path.len(); // keep for string trimming later
+            // tags still attached to pages might be missing from the tag list, so include both
+            let mut rows = tags
+                .iter()
+                .map(|t| (t.tag.as_str(), t.title.as_deref()))
+                .chain(counts.keys().map(|t| (*t, None)))
+                .unique_by(|(tag, _)| *tag)
+                .map(|(tag, title)| (tag, title, counts.get(tag).copied().unwrap_or(0)))
+                .collect::<Vec<_>>();
+
+            match sort {
+                TagSort::Name => rows.sort_by(|a, b| a.0.cmp(b.0)),
+                TagSort::Count => rows.sort_by(|a, b| b.2.cmp(&a.2).then(a.0.cmp(b.0))),
+            }
 
-            let pages = wiki.list_pages(&path, tags).await?;
+            term.write_line("Tag\tPages\tTitle")?;
+            term.write_line(
+                &rows
+                    .iter()
+                    .map(|(tag, title, count)| format!("{}\t{}\t{}", tag, count, title.unwrap_or("")))
+                    .join("\n"),
+            )?;
+            Exit::Success
+        }
+        Command::History { id } => {
+            let wiki = connect(&cfg, &app.global_opts).await?;
+            let history = wiki.get_page_history(id).await?;
+            let trail = history.trail.into_iter().flatten().flatten().collect::<Vec<_>>();
 
-            term.write_line(&format!(
-                "{} {}  {} {} {} {}.",
-                "[3/3]".if_supports_color(Stream::Stdout, |text| text.style(styles.scaffold)),
-                Emoji("📝", ""),
-                "Formatting".if_supports_color(Stream::Stdout, |text| text.style(styles.message)),
-                &pages
-                    .pages
-                    .len()
-                    .if_supports_color(Stream::Stdout, |text| text.style(styles.output)),
-                "matching pages"
-                    .if_supports_color(Stream::Stdout, |text| text.style(styles.message)),
-                match app.global_opts.verbose {
-                    0 => String::new(),
-                    _ => format!(
-                        "{} {} {}",
-                        "out of "
-                            .if_supports_color(Stream::Stdout, |text| text.style(styles.message)),
-                        pages
-                            .pages_returned
-                            .if_supports_color(Stream::Stdout, |text| text.style(styles.output)),
-                        "returned by wiki"
-                            .if_supports_color(Stream::Stdout, |text| text.style(styles.message))
-                    ),
-                }
-            ))?;
+            if app.global_opts.verbose > 0 {
+                term.write_line(&format!("Showing {} of {} versions.", trail.len(), history.total))?;
+            }
 
-            let header = "ID\tPath\tTitle\tTags"
-                .if_supports_color(Stream::Stdout, |text| text.style(styles.message));
+            term.write_line("Version\tDate\tAuthor\tAction")?;
+            term.write_line(
+                &trail
+                    .iter()
+                    .map(|v| {
+                        format!(
+                            "{}\t{}\t{}\t{}",
+                            v.version_id,
+                            v.version_date.to_rfc3339(),
+                            v.author_name,
+                            v.action_type
+                        )
+                    })
+                    .join("\n"),
+            )?;
+            Exit::Success
+        }
+        Command::Restore { id, version, yes } => {
+            let wiki = connect(&cfg, &app.global_opts).await?;
 
-            let null_title = "[Untitled]";
+            let proceed = yes || confirm(&term, &format!("Are you sure you want to restore page {} to version {}?", id, version))?;
 
-            let max_path = match pages.pages.iter().map(|p| p.path.len()).max() {
-                Some(s) => s - trim,
-                None => 50,
-            };
+            if !proceed {
+                bail!(Aborted("User was not sure they want to restore the page."))
+            }
 
-            let lines = pages
-                .pages
-                .into_iter()
-                .map(|p| {
-                    format!(
-                        "{}\t{}\t{} ({})",
-                        p.id,
-                        console::pad_str(
-                            &p.path[trim..],
-                            max_path,
-                            console::Alignment::Left,
-                            Some("…")
-                        ),
-                        match p.title {
-                            Some(t) => t,
-                            None => null_title.to_string(),
-                        },
-                        match p.tags {
-                            Some(ts) => ts.into_iter().flatten().join(", "),
-                            None => String::new(),
-                        }
-                    )
-                })
-                .join("\n");
+            let status = wiki.restore_page(id, version).await?;
 
-            term.write_line(&format!("{}", &header))?;
-            term.write_line(&lines)?;
+            match status.succeeded {
+                true => {
+                    term.write_line(&format!("Page {} has been restored to version {}.", id, version))?;
+                    Exit::Success
+                }
+                false => {
+                    term.write_line(&format!(
+                        "Restoring page {} failed. Code: {} Slug: {} Message: {}",
+                        id,
+                        status.error_code,
+                        status.slug,
+                        status.message.unwrap_or_default(),
+                    ))?;
+                    Exit::AllFailed
+                }
+            }
         }
         Command::Move {
             path,
             destination,
+            path_transform,
             tags,
+            source_locale,
+            dest_locale,
+            from_stdin,
+            nest,
+            as_folder,
+            as_prefix,
+            fail_fast,
+            timeout_per_page,
+            copy_then_delete,
+            rollback_on_partial_failure,
+            yes,
+            force,
+            select,
+            summary_only,
+            deep_scan,
+            no_safety_check,
+            fail_on_private,
+            max_pages,
+            confirm_threshold,
+            verify,
+            report_file,
+            audit,
+            include_system_pages,
+            dry_run,
+            plan_file,
         } => {
-            term.write_line(&format!(
-                "[1/3] {}  Preparing to connect to the Wiki",
-                Emoji("☎️", "")
-            ))?;
+            let path = path.map(|p| resolve_path(base_path, &p)).unwrap_or_default();
+            // the plan alone goes to stdout, for CI to keep
+            let plan_json = dry_run && app.global_opts.format == Format::Json;
+            let locale = dest_locale.clone()
+                .or_else(|| app.global_opts.locale.clone())
+                .or_else(|| cfg.locale.clone())
+                .unwrap_or_else(|| DEFAULT_LOCALE.to_owned());
+            let term = match plan_json {
+                true => Term::stderr(),
+                false => term,
+            };
+            let plan = match &plan_file {
+                Some(file) => Some((file, read_plan(file)?)),
+                None => None,
+            };
+            let destination = destination.map(|d| resolve_path(base_path, &d));
+            let tags = tags_or_default(tags, &cfg);
+            let checks = PageChecks {
+                action: &MOVING,
+                term: &term,
+                reporter: reporter.as_ref(),
+                cfg: &cfg,
+                template,
+                max_path_width,
+            };
+
+            if select && !Term::stderr().is_term() {
+                bail!("--select needs an interactive terminal to pick pages in.")
+            }
+
+            reporter.connecting()?;
 
+            // checked & announced alongside the listing, to save a round trip
             let wiki = Wiki::new(wiki_config(&cfg, &app.global_opts)?);
 
-            term.write_line(&format!(
-                "[2/3] {}  Finding all pages beginning with {} {}.",
-                Emoji("🔍", ""),
-                &path,
-                match &tags {
-                    Some(tags) => format!("which have the tags: {}", &tags.join(", ")),
-                    None => String::new(),
+            let ListPages {
+                mut pages,
+                pages_returned,
+            } = match (&plan, from_stdin) {
+                (Some((file, plan)), _) => {
+                    reporter.finding("Reading the moves planned in", &file.display().to_string(), None)?;
+                    check_and_announce(&wiki, &app.global_opts).await;
+                    ListPages { pages: plan.iter().map(PlannedMove::page).collect(), pages_returned: plan.len() }
                 }
-            ))?;
-            let trim = path.len(); // keep for string trimming later
+                (None, true) => {
+                    reporter.finding("Finding the pages listed on", "stdin", None)?;
+                    let ((), pages) = tokio::join!(
+                        check_and_announce(&wiki, &app.global_opts),
+                        pages_from_stdin(&wiki, base_path)
+                    );
+                    let pages = pages?;
+                    let outside = pages.pages.iter().filter(|p| !p.path.starts_with(path.as_str())).collect::<Vec<_>>();
+                    if !nest && path_transform.is_none() && !outside.is_empty() {
+                        bail!(
+                            "These pages don't begin with `{}`, so it can't be replaced. Use a shorter prefix, or --nest:\n{}",
+                            path,
+                            outside.iter().map(|p| &p.path).join("\n")
+                        )
+                    }
+                    pages
+                }
+                (None, false) => {
+                    reporter.finding("Finding all pages beginning with", &path, tags.as_deref())?;
+                    let ((), pages) = tokio::join!(
+                        check_and_announce(&wiki, &app.global_opts),
+                        list_tagged(&wiki, &path, tags.clone(), app.global_opts.tag_mode, source_locale.clone())
+                    );
+                    pages?
+                }
+            };
+            // pages from stdin may not share the prefix, so show their paths in full
+            let trim = match from_stdin || plan.is_some() {
+                true => String::new(),
+                false => path.clone(),
+            };
 
-            let lib::ListPages {
-                pages,
-                pages_returned,
-            } = wiki.list_pages(&path, tags).await?;
+            if let Some(locale) = &source_locale {
+                pages.retain(|p| &p.locale == locale);
+            }
 
-            term.write_line(&format!(
-                "[3/3] {}  Formatting {} matching pages {}.",
-                Emoji("📝", ""),
-                &pages.len(),
-                match app.global_opts.verbose {
-                    0 => String::new(),
-                    _ => format!("out of {} returned by wiki", pages_returned),
+            let hint = tag_mode_hint(tags.as_ref(), app.global_opts.tag_mode);
+            let listed = ListPages { pages, pages_returned };
+            let pages = match checks.narrow(listed, &path, hint, include_system_pages, max_pages, force)? {
+                Some(pages) => pages,
+                None => return Ok(Exit::Success),
+            };
+
+            // a plan only has paths, which are shown below
+            if !summary_only && plan.is_none() {
+                if template.is_none() {
+                    term.write_line(HEADER)?;
                 }
-            ))?;
+                term.write_line(&format_pages(&pages, template, &trim, max_path_width).join("\n"))?;
+            }
+
+            let pages = match select {
+                false => pages,
+                true => {
+                    let items = pages
+                        .iter()
+                        .map(|p| format!(
+                            "{}\t{}",
+                            p.path,
+                            p.title.as_deref().unwrap_or(UNTITLED)
+                        ))
+                        .collect::<Vec<_>>();
+                    flush_output(&term)?;
+                    let chosen = dialoguer::MultiSelect::new()
+                        .with_prompt("Choose the pages to move (space to toggle, enter to accept)")
+                        .items(&items)
+                        .defaults(&vec![true; items.len()])
+                        .interact_on(&Term::stderr())?;
+                    pages
+                        .into_iter()
+                        .enumerate()
+                        .filter(|(i, _)| chosen.contains(i))
+                        .map(|(_, p)| p)
+                        .collect::<Vec<_>>()
+                }
+            };
 
-            let header = "ID\tPath\tTitle\tTags";
+            if pages.is_empty() {
+                term.write_line("No pages were selected, so there's nothing to move.")?;
+                return Ok(Exit::Success);
+            }
 
-            let null_title = "[Untitled]";
+            let (new_path, to) = match (&plan, path_transform, destination) {
+                (Some((file, plan)), _, _) => {
+                    let paths = plan.iter().map(|m| (m.old_path.clone(), m.new_path.clone())).collect();
+                    (format!("the plan in {}", file.display()), Destination::Exact(paths))
+                }
+                (None, Some(transform), _) => (transform.to_string(), Destination::Transform(transform)),
+                (None, None, Some(destination)) => {
+                    // nesting keeps the whole path, which is replacing an empty prefix
+                    let (prefix, destination) = match nest {
+                        true => (String::new(), format!("{}/", destination.trim_end_matches('/'))),
+                        false => (path.clone(), destination),
+                    };
+                    // --as-folder is the default, so it only has to be given to be explicit
+                    let to = Destination::Prefix { prefix, destination, as_folder: as_folder || !as_prefix };
+                    (to.of_path(&path), to)
+                }
+                (None, None, None) => bail!("Give a --destination or a --path-transform to move the pages to."),
+            };
 
-            let max_path = match pages.iter().map(|p| p.path.len()).max() {
-                Some(s) => s - trim,
-                None => 50,
+            let pages = match &to {
+                Destination::Prefix { .. } if summary_only => {
+                    term.write_line(&format!("About to move {} pages from {} to {}.", pages.len(), &path, &new_path))?;
+                    pages
+                }
+                Destination::Prefix { .. } => {
+                    term.write_line(&format!(
+                        "{} All of these pages will be relocated from {}… to {}…!",
+                        Emoji("📎", ""),
+                        &path,
+                        &new_path
+                    ))?;
+                    pages
+                }
+                // every new path is shown, even with --yes, as they're harder to predict (unless --summary-only)
+                Destination::Transform(_) | Destination::Exact(_) => {
+                    let (pages, unchanged): (Vec<_>, Vec<_>) = pages.into_iter().partition(|p| to.of(p) != p.path);
+                    if !unchanged.is_empty() {
+                        term.write_line(&format!(
+                            "{} pages are left where they are, as {} doesn't change their path{}",
+                            unchanged.len(),
+                            new_path,
+                            match app.global_opts.verbose {
+                                0 => ".".to_owned(),
+                                _ => format!(":\n{}", unchanged.iter().map(|p| format!("{} (no-op)", p.path)).join("\n")),
+                            }
+                        ))?;
+                    }
+                    if pages.is_empty() {
+                        term.write_line("So there's nothing to move.")?;
+                        return Ok(Exit::Success);
+                    }
+                    match (summary_only, path.is_empty()) {
+                        (true, true) => term.write_line(&format!("About to move {} pages by {}.", pages.len(), new_path))?,
+                        (true, false) => term.write_line(&format!(
+                            "About to move {} pages from {} by {}.",
+                            pages.len(),
+                            &path,
+                            new_path
+                        ))?,
+                        (false, _) => term.write_line(&format!(
+                            "{} These pages will be relocated by {}:\n{}",
+                            Emoji("📎", ""),
+                            new_path,
+                            pages.iter().map(|p| path_diff(&p.path, &to.of(p))).join("\n")
+                        ))?,
+                    }
+                    pages
+                }
             };
 
-            let lines = pages
-                .iter()
-                .map(|p| -> String {
-                    format!(
-                        "{}\t{}\t{} ({})",
-                        p.id,
-                        console::pad_str(
-                            &p.path[trim..],
-                            max_path,
-                            console::Alignment::Left,
-                            Some("…")
+            let collisions = find_collisions(&wiki, &pages, &to).await?;
+
+            if !collisions.is_empty() {
+                term.write_line(&format!(
+                    "{} The following moves collide with another page:\n{}",
+                    Emoji("💥", ""),
+                    collisions.join("\n")
+                ))?;
+                match (force || dry_run, yes) {
+                    (true, _) => {}
+                    (false, true) => bail!("Refusing to move colliding pages without --force."),
+                    (false, false) => {
+                        let proceed = confirm(&term, "Colliding moves will fail or overwrite pages.\nAre you really sure you want to go ahead?")?;
+
+                        if !proceed {
+                            bail!(Aborted("User was not really sure they want to move colliding pages."))
+                        }
+                    }
+                }
+            }
+
+            // a plan's pages are checked again, as the markers may have changed since
+            let private_pages = checks.private(&wiki, &pages, no_safety_check, deep_scan, fail_on_private).await?;
+
+            if dry_run {
+                if plan_json {
+                    let plan = pages
+                        .iter()
+                        .map(|p| PlannedMove {
+                            id: p.id,
+                            old_path: p.path.clone(),
+                            new_path: to.of(p),
+                            locale: p.locale.clone(),
+                            tags: p.tags.iter().flatten().flatten().cloned().collect(),
+                            would_flag_private: private_pages.iter().any(|pp| pp.id == p.id),
+                        })
+                        .collect::<Vec<_>>();
+                    Term::stdout().write_line(&cynic::serde_json::to_string_pretty(&plan)?)?;
+                } else if !summary_only {
+                    term.write_line(&format_dry_run(&pages, &to))?;
+                }
+                term.write_line(&format!(
+                    "Dry run, so nothing was moved. {} pages would move, {} of them private.",
+                    pages.len(),
+                    private_pages.len()
+                ))?;
+                return Ok(Exit::Success);
+            }
+
+            // small moves can go ahead without asking, private pages are still confirmed below
+            let confirm_threshold = confirm_threshold.or(cfg.confirm_threshold).unwrap_or(0);
+            let below_threshold = pages.len() < confirm_threshold;
+            if below_threshold && !yes {
+                term.write_line(&format!(
+                    "Not asking for confirmation, as fewer than {} pages will move.",
+                    confirm_threshold
+                ))?;
+            }
+
+            checks.confirm(yes || below_threshold, &private_pages, &trim)?;
+
+            // a page's locale decides which language tree it's in, and so who can see it
+            let relocated = pages.iter().filter(|p| p.locale != locale).collect::<Vec<_>>();
+            if !relocated.is_empty() && !force {
+                Term::stderr().write_line(&format!(
+                    "{} {}\n{}",
+                    Emoji("⚠️ ", ""),
+                    format!("{} pages will change locale, to `{}`:", relocated.len(), locale)
+                        .if_supports_color(Stream::Stderr, |text| text.style(Style::new().red().bold())),
+                    relocated.iter().map(|p| format!("{} ({} -> {})", p.path, p.locale, locale)).join("\n")
+                ))?;
+                let proceed = confirm(&term, "Changing a page's locale moves it to another language tree, and may change who can access it.\nAre you really sure you want to change their locale?")?;
+
+                if !proceed {
+                    bail!(Aborted("User was not really sure they want to change the locale of pages."))
+                }
+            }
+
+            // on Ctrl-C, stop sending moves but let those in flight finish. Listening for it
+            // stops Ctrl-C killing the process, so from then on a second one exits at once,
+            // as does one after the moves were sent, e.g. while verifying or rolling back
+            let (interrupted, interrupt) = tokio::sync::oneshot::channel::<()>();
+            tokio::spawn(async move {
+                if tokio::signal::ctrl_c().await.is_err() {
+                    return;
+                }
+                if interrupted.send(()).is_ok() && tokio::signal::ctrl_c().await.is_err() {
+                    return;
+                }
+                eprintln!("Interrupted, so exiting without waiting for the requests in flight. Some pages may have moved.");
+                std::process::exit(Exit::PartialFailure as i32);
+            });
+            let interrupt = async {
+                if interrupt.await.is_err() {
+                    std::future::pending::<()>().await
+                }
+            };
+            let started = std::time::Instant::now();
+            let moves = match copy_then_delete {
+                true => wiki.copy_then_delete_pages(&pages, &to, Some(&locale), interrupt).await,
+                false => wiki.move_pages(&pages, &to, Some(&locale), fail_fast, timeout_per_page, interrupt).await,
+            };
+            let moves = match moves {
+                Ok(moves) => moves,
+                Err(WikiError::PartialMove(moves)) => {
+                    if let Some(file) = &report_file {
+                        write_move_report(file, &pages, &to, &locale, &moves)?;
+                    }
+                    if audit {
+                        print_move_audit(&term, &pages, &to, &locale, &moves)?;
+                    }
+                    if rollback_on_partial_failure {
+                        roll_back(&wiki, &term, &app.global_opts, &pages, &moves).await?;
+                    }
+                    return Err(WikiError::PartialMove(moves).into());
+                }
+                Err(e) => return Err(e.into()),
+            };
+            if let Some(file) = &report_file {
+                write_move_report(file, &pages, &to, &locale, &moves)?;
+            }
+            let elapsed = started.elapsed();
+            // pages are moved in order under --fail-fast, so we know exactly where it stopped
+            let stopped = fail_fast && moves.failures.is_some();
+            // stopping at the first page under --fail-fast is all of them failing, as nothing moved
+            let mut exit = match (moves.not_attempted, stopped) {
+                (0, _) | (_, true) => Exit::from_failures(moves.success_count, &moves.failures),
+                _ => Exit::PartialFailure,
+            };
+
+            if moves.not_attempted > 0 && !stopped {
+                term.write_line(&format!(
+                    "Interrupted. {} pages were not attempted. {} pages had been moved:\n{}",
+                    moves.not_attempted,
+                    moves.moved.len(),
+                    pages.iter().filter(|p| moves.moved.contains(&p.id)).map(|p| &p.path).join("\n")
+                ))?;
+            }
+
+            if stopped {
+                term.write_line(&format!(
+                    "Stopped at the first failure, moving {}. {} pages were not attempted. {} pages had already been moved:\n{}",
+                    &pages[moves.success_count].path,
+                    moves.not_attempted,
+                    moves.success_count,
+                    pages[..moves.success_count].iter().map(|p| &p.path).join("\n")
+                ))?;
+            }
+
+            match &moves.failures {
+                None if moves.not_attempted > 0 => {}
+                None => {
+                    term.write_line(&match &to {
+                        Destination::Prefix { .. } => format!(
+                            "All pages have been moved successfully from `{}` to `{}`.",
+                            path, new_path
+                        ),
+                        Destination::Transform(_) => format!("All pages have been moved successfully by `{}`.", new_path),
+                        Destination::Exact(_) => format!("All pages have been moved successfully by {}.", new_path),
+                    })?;
+                }
+                Some(fails) => {
+                    report_failures(
+                        &term,
+                        app.global_opts.json_errors,
+                        app.global_opts.verbose > 0,
+                        &format!(
+                            "{} failures occurred during moves. {} successes occurred. Pages may be inconsistently moved.",
+                            fails.len(),
+                            moves.success_count
                         ),
-                        match &p.title {
-                            Some(t) => t,
-                            None => null_title,
-                        },
-                        match &p.tags {
-                            Some(ts) => ts.into_iter().flatten().join(", "),
-                            None => String::new(),
+                        fails.iter().map(|rs| (None, rs)),
+                    )?;
+                }
+            }
+            if copy_then_delete {
+                term.write_line("Steps per page:")?;
+                term.write_line(
+                    &moves
+                        .outcomes
+                        .iter()
+                        .map(|o| format!("{} -> {}: {}", o.from, o.to, o.steps.as_deref().unwrap_or("request failed")))
+                        .join("\n"),
+                )?;
+            }
+            term.write_line(&format!(
+                "Moved {} pages in {:.1}s ({:.1} pages/s)",
+                moves.success_count,
+                elapsed.as_secs_f64(),
+                moves.success_count as f64 / elapsed.as_secs_f64()
+            ))?;
+
+            if app.global_opts.verbose > 0 && !moves.latencies.is_empty() {
+                term.write_line(&format!("Per page: {}", LatencySummary::of(&moves.latencies)))?;
+            }
+
+            if audit {
+                print_move_audit(&term, &pages, &to, &locale, &moves)?;
+            }
+
+            let rolled_back = rollback_on_partial_failure && moves.failures.is_some();
+            if rolled_back {
+                roll_back(&wiki, &term, &app.global_opts, &pages, &moves).await?;
+            }
+
+            if verify && !rolled_back && !moves.moved.is_empty() {
+                reporter.note(&format!(
+                    "Checking the {} moved pages landed, this makes a request per page.",
+                    moves.moved.len()
+                ))?;
+                let moved = pages.iter().filter(|p| moves.moved.contains(&p.id));
+                let misplaced = wiki.verify_moves(moved, &to).await?;
+                match misplaced.is_empty() {
+                    true => term.write_line("Every moved page is at its destination.")?,
+                    false => {
+                        term.write_line(&format!(
+                            "{} pages were reported moved, but the wiki doesn't have them at their destination:\n{}",
+                            misplaced.len(),
+                            misplaced
+                                .iter()
+                                .map(|(p, actual)| format!(
+                                    "{} -> {} (the wiki says it's at {})",
+                                    p.path,
+                                    to.of(p),
+                                    actual
+                                ))
+                                .join("\n")
+                        ))?;
+                        exit = Exit::PartialFailure;
+                    }
+                }
+
+                // a change of locale is only expected into the locale asked for
+                let moved = pages.iter().filter(|p| moves.moved.contains(&p.id));
+                let changes = wiki.metadata_changes(moved).await?;
+                for change in changes.iter().filter(|c| c.locale.is_some() && c.locale.as_deref() != Some(locale.as_str())) {
+                    reporter.warning(&format!(
+                        "{} was in locale `{}`, and is now in `{}`.",
+                        to.of(change.page),
+                        change.page.locale,
+                        change.locale.as_deref().unwrap_or_default()
+                    ))?;
+                }
+                if app.global_opts.verbose > 0 {
+                    let fields = [
+                        ("locale", changes.iter().filter(|c| c.locale.is_some()).count()),
+                        ("title", changes.iter().filter(|c| c.title).count()),
+                        ("tags", changes.iter().filter(|c| c.tags).count()),
+                        ("last updated time", changes.iter().filter(|c| c.updated_at).count()),
+                    ];
+                    let (changed, kept): (Vec<_>, Vec<_>) = fields.iter().partition(|(_, n)| *n > 0);
+                    term.write_line(&format!(
+                        "Checked the metadata of {} moved pages. Kept: {}. Changed: {}.",
+                        changes.len(),
+                        if kept.is_empty() { "nothing".to_owned() } else { kept.iter().map(|(f, _)| f).join(", ") },
+                        if changed.is_empty() {
+                            "nothing".to_owned()
+                        } else {
+                            changed.iter().map(|(f, n)| format!("{} (on {})", f, n)).join(", ")
                         }
-                    )
-                })
-                .join("\n");
+                    ))?;
+                }
+            }
+            exit
+        }
+        Command::Copy {
+            path,
+            destination,
+            tags,
+            overwrite,
+            as_folder,
+            as_prefix,
+        } => {
+            let path = resolve_path(base_path, &path);
+            let destination = resolve_path(base_path, &destination);
+            let tags = tags_or_default(tags, &cfg);
+
+            reporter.connecting()?;
+
+            let wiki = connect(&cfg, &app.global_opts).await?;
+
+            reporter.finding("Finding all pages beginning with", &path, tags.as_deref())?;
+            let trim = path.clone(); // keep for string trimming later
 
-            term.write_line(&header)?;
-            term.write_line(&lines)?;
+            let ListPages {
+                pages,
+                pages_returned,
+            } = list_tagged(&wiki, &path, tags, app.global_opts.tag_mode, None).await?;
+
+            reporter.pages_found("Formatting", pages.len(), "matching pages", Some(pages_returned))?;
+
+            if template.is_none() {
+                term.write_line(HEADER)?;
+            }
+            term.write_line(&format_pages(&pages, template, &trim, max_path_width).join("\n"))?;
 
             term.write_line(&format!(
-                "{} All of these pages will be relocated from {}… to {}…!",
+                "{} All of these pages will be copied from {}… to {}…{}!",
                 Emoji("📎", ""),
                 &path,
-                &destination
+                &destination,
+                match overwrite {
+                    true => ", overwriting any pages already there",
+                    false => "",
+                }
             ))?;
 
-            let proceed = Confirm::new()
-                .with_prompt("Are you sure you want to do this?")
-                .interact_on(&Term::stderr())?;
+            let proceed = confirm(&term, "Are you sure you want to do this?")?;
 
             if !proceed {
-                bail!("User was not sure they want to do this.")
-            } // is it an error?
+                bail!(Aborted("User was not sure they want to do this."))
+            }
+
+            let copies = wiki.copy_pages(&pages, &path, &destination, as_folder || !as_prefix, overwrite).await?;
+            let exit = Exit::from_failures(copies.success_count, &copies.failures);
 
-            let private_pages = wiki.safety_check_private(pages.iter()).await;
+            if !copies.skipped.is_empty() {
+                term.write_line(&format!(
+                    "{} pages were skipped as they already exist at the destination:\n{}",
+                    copies.skipped.len(),
+                    copies.skipped.join("\n")
+                ))?;
+            }
 
-            let check_private = match private_pages {
-                Some(pgs) => {
-                    term.write_line(
-                        "The following pages you intend to move are marked as private:",
+            match copies.failures {
+                None => {
+                    term.write_line(&format!(
+                        "{} pages have been copied successfully from `{}` to `{}`.",
+                        copies.success_count, path, destination
+                    ))?;
+                }
+                Some(fails) => {
+                    report_failures(
+                        &term,
+                        app.global_opts.json_errors,
+                        app.global_opts.verbose > 0,
+                        &format!(
+                            "{} failures occurred during copies. {} successes occurred.",
+                            fails.len(),
+                            copies.success_count
+                        ),
+                        fails.iter().map(|rs| (None, rs)),
                     )?;
-                    let lines = pgs
-                        .map(|p| -> String {
-                            format!(
-                                "{}\t{}\t{} ({})",
-                                p.id,
-                                console::pad_str(
-                                    &p.path[trim..],
-                                    max_path,
-                                    console::Alignment::Left,
-                                    Some("…")
-                                ),
-                                match &p.title {
-                                    Some(t) => t,
-                                    None => null_title,
-                                },
-                                match &p.tags {
-                                    Some(ts) => ts.into_iter().flatten().join(", "),
-                                    None => String::new(),
-                                }
-                            )
-                        })
-                        .join("\n");
-                    term.write_line(&lines)?;
-                    true
                 }
-                None => false,
+            }
+            exit
+        }
+        Command::Delete {
+            path,
+            tags,
+            yes,
+            force,
+            max_pages,
+            include_system_pages,
+            dry_run,
+            no_safety_check,
+            fail_on_private,
+        } => {
+            let path = resolve_path(base_path, &path);
+            let tags = tags_or_default(tags, &cfg);
+            let checks = PageChecks {
+                action: &DELETING,
+                term: &term,
+                reporter: reporter.as_ref(),
+                cfg: &cfg,
+                template,
+                max_path_width,
             };
 
-            if check_private {
-                let proceed = Confirm::new()
-                        .with_prompt("Moving private pages may change who can access them.\nAre you really sure you want to move private pages?")
-                        .interact_on(&Term::stderr())?;
+            reporter.connecting()?;
 
-                if !proceed {
-                    bail!("User was not really sure they want to move private pages.")
-                }
+            let wiki = connect(&cfg, &app.global_opts).await?;
+
+            reporter.finding("Finding all pages beginning with", &path, tags.as_deref())?;
+            let trim = path.clone(); // keep for string trimming later
+
+            let listed = list_tagged(&wiki, &path, tags.clone(), app.global_opts.tag_mode, None).await?;
+            let hint = tag_mode_hint(tags.as_ref(), app.global_opts.tag_mode);
+            let pages = match checks.narrow(listed, &path, hint, include_system_pages, max_pages, force)? {
+                Some(pages) => pages,
+                None => return Ok(Exit::Success),
+            };
+
+            if template.is_none() {
+                term.write_line(HEADER)?;
+            }
+            term.write_line(&format_pages(&pages, template, &trim, max_path_width).join("\n"))?;
+
+            let private_pages = checks.private(&wiki, &pages, no_safety_check, false, fail_on_private).await?;
+
+            if dry_run {
+                term.write_line(&format!(
+                    "Dry run, so nothing was deleted. {} pages would be deleted, {} of them private{}",
+                    pages.len(),
+                    private_pages.len(),
+                    match private_pages.is_empty() {
+                        true => ".".to_owned(),
+                        false => format!(":\n{}", format_pages(private_pages.iter().copied(), template, &trim, max_path_width).join("\n")),
+                    }
+                ))?;
+                return Ok(Exit::Success);
             }
 
-            let moves = wiki.move_pages(&pages, &path, &destination).await?;
+            term.write_line(&format!(
+                "{} All of these pages will be deleted!",
+                Emoji("🗑️ ", "")
+            ))?;
+
+            checks.confirm(yes, &private_pages, &trim)?;
+
+            let deletes = wiki.delete_pages(&pages).await?;
+            let exit = Exit::from_failures(deletes.success_count, &deletes.failures);
 
-            match moves.failures {
+            match deletes.failures {
                 None => {
                     term.write_line(&format!(
-                        "All pages have been moved successfully from `{}` to `{}`.",
-                        path, destination
+                        "All {} pages beginning with `{}` have been deleted.",
+                        deletes.success_count, path
                     ))?;
                 }
                 Some(fails) => {
-                    term.write_line(&format!(
-                        "{} failures occured during moves. {} successes occured. Pages may be inconsistently moved.", 
-                        fails.len(),
-                        moves.success_count
-                    ))?;
-                    let blank = String::new();
-                    term.write_line(
-                        &fails
-                            .iter()
-                            .map(|rs| {
-                                format!(
-                                    "Code: {} Slug: {} Message: {}",
-                                    rs.error_code,
-                                    rs.slug,
-                                    &rs.message.as_ref().unwrap_or(&blank),
-                                )
-                            })
-                            .join("\n"),
+                    report_failures(
+                        &term,
+                        app.global_opts.json_errors,
+                        app.global_opts.verbose > 0,
+                        &format!(
+                            "{} failures occurred during deletes. {} successes occurred. Some pages may be left behind.",
+                            fails.len(),
+                            deletes.success_count
+                        ),
+                        fails.iter().map(|rs| (None, rs)),
                     )?;
                 }
             }
+            exit
+        }
+    };
+    Ok(exit)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn migrate_records_the_version_of_a_v0_config() {
+        // written before versioning, so there's no `version` key
+        let mut config: WikcliConfig = cynic::serde_json::from_value(json!({
+            "api_key": "k",
+            "endpoint": "https://wiki.example.com/graphql",
+            "max_pages": 50,
+        })).unwrap();
+
+        assert_eq!(config.migrate(), Some(0));
+        assert_eq!(config.version, Some(CONFIG_VERSION));
+        assert_eq!(config.api_key.as_deref(), Some("k"));
+        assert_eq!(config.endpoint.as_deref(), Some("https://wiki.example.com/graphql"));
+        assert_eq!(config.max_pages, Some(50));
+
+        // already current, so nothing to report the second time
+        assert_eq!(config.migrate(), None);
+        assert_eq!(config.version, Some(CONFIG_VERSION));
+    }
+
+    fn page(id: i32, path: &str, tags: &[&str]) -> PageListItem {
+        PageListItem {
+            id,
+            path: path.to_owned(),
+            locale: DEFAULT_LOCALE.to_owned(),
+            tags: Some(tags.iter().map(|t| Some(t.to_string())).collect()),
+            title: None,
+            content_type: "markdown".to_owned(),
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
         }
     }
-    Ok(())
+
+    fn tagged_fixture() -> Vec<PageListItem> {
+        vec![
+            page(1, "docs/guide", &["guide", "howto"]),
+            page(2, "docs/howto", &["howto"]),
+            page(3, "docs/notes", &[]),
+            page(4, "docs/Guide", &["Guide"]),
+        ]
+    }
+
+    fn ids(pages: &[PageListItem]) -> Vec<i32> {
+        pages.iter().map(|p| p.id).collect()
+    }
+
+    #[test]
+    fn tag_mode_all_keeps_pages_with_every_tag() {
+        let mut pages = tagged_fixture();
+        retain_tagged(&mut pages, &["guide".to_owned(), "howto".to_owned()], TagMode::All);
+        assert_eq!(ids(&pages), [1]);
+    }
+
+    #[test]
+    fn tag_mode_any_keeps_pages_with_one_of_the_tags() {
+        let mut pages = tagged_fixture();
+        retain_tagged(&mut pages, &["guide".to_owned(), "howto".to_owned()], TagMode::Any);
+        // tags are case sensitive, like Wiki.js
+        assert_eq!(ids(&pages), [1, 2]);
+    }
+
+    fn checks<'a>(term: &'a Term, cfg: &'a WikcliConfig) -> PageChecks<'a> {
+        PageChecks {
+            action: &MOVING,
+            term,
+            reporter: &QuietReporter,
+            cfg,
+            template: None,
+            max_path_width: None,
+        }
+    }
+
+    #[test]
+    fn an_empty_match_stops_before_asking() {
+        let (term, cfg): (_, WikcliConfig) = (Term::stderr(), ::std::default::Default::default());
+        let listed = ListPages { pages: Vec::new(), pages_returned: 4 };
+
+        // `None` is what skips `PageChecks::confirm`, even when no pages are allowed at all
+        let narrowed = checks(&term, &cfg).narrow(listed, "docs/", None, false, Some(0), false);
+        assert!(matches!(narrowed, Ok(None)));
+    }
+
+    #[test]
+    fn a_match_over_the_limit_is_refused() {
+        let (term, cfg): (_, WikcliConfig) = (Term::stderr(), ::std::default::Default::default());
+        let listed = || ListPages { pages: tagged_fixture(), pages_returned: 4 };
+
+        assert!(checks(&term, &cfg).narrow(listed(), "docs/", None, false, Some(3), false).is_err());
+        let forced = checks(&term, &cfg).narrow(listed(), "docs/", None, false, Some(3), true).unwrap();
+        assert_eq!(ids(&forced.unwrap()), [1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn an_empty_match_is_explained() {
+        let hint = tag_mode_hint(Some(&vec!["guide".to_owned(), "howto".to_owned()]), TagMode::All);
+        assert_eq!(
+            nothing_matched(&[], "docs/", Some("move"), hint).as_deref(),
+            Some("No pages matched prefix `docs/`, so there's nothing to move.\nNo pages have ALL of [guide, howto]; did you mean any? Try --tag-mode any.")
+        );
+        assert_eq!(nothing_matched(&[], "docs/", None, None).as_deref(), Some("No pages matched prefix `docs/`."));
+    }
+
+    #[test]
+    fn a_match_goes_on() {
+        assert_eq!(nothing_matched(&tagged_fixture(), "docs/", Some("move"), None), None);
+    }
+
+    #[test]
+    fn content_must_match_both_the_text_and_the_regex() {
+        let content = "# Setup\nRun `make install`\nSee ticket OPS-1234";
+        let regex = Regex::new(r"[A-Z]+-\d+").unwrap();
+
+        assert_eq!(matching_line(content, Some("make"), Some(&regex)).as_deref(), Some("See ticket OPS-1234"));
+        assert_eq!(matching_line(content, Some("cmake"), Some(&regex)), None);
+        assert_eq!(matching_line(content, None, Some(&Regex::new("^Run").unwrap())).as_deref(), Some("Run `make install`"));
+        assert_eq!(matching_line(content, Some("install"), None).as_deref(), Some("Run `make install`"));
+    }
+
+    #[test]
+    fn tag_mode_any_leaves_the_tags_to_match_here() {
+        let tags = Some(vec!["guide".to_owned()]);
+        assert_eq!(TagMode::All.split(tags.clone()), (tags.clone(), None));
+        assert_eq!(TagMode::Any.split(tags.clone()), (None, tags));
+    }
 }